@@ -0,0 +1,3 @@
+ALTER TABLE todos ADD COLUMN due_date TIMESTAMPTZ;
+
+CREATE INDEX idx_due_date ON todos(due_date);
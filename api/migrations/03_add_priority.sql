@@ -0,0 +1,5 @@
+CREATE TYPE todo_priority AS ENUM ('low', 'medium', 'high');
+
+ALTER TABLE todos ADD COLUMN priority todo_priority NOT NULL DEFAULT 'medium';
+
+CREATE INDEX idx_priority ON todos(priority);
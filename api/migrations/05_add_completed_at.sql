@@ -0,0 +1,4 @@
+ALTER TABLE todos ADD COLUMN completed_at TIMESTAMPTZ;
+
+-- Backfill rows that were already completed with their last update time
+UPDATE todos SET completed_at = updated_at WHERE completed;
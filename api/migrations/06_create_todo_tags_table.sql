@@ -0,0 +1,7 @@
+CREATE TABLE todo_tags (
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    tag VARCHAR(50) NOT NULL,
+    PRIMARY KEY (todo_id, tag)
+);
+
+CREATE INDEX idx_todo_tags_tag ON todo_tags(tag);
@@ -0,0 +1,4 @@
+-- Owner of the todo; NULL for todos created while JWT authentication is disabled
+ALTER TABLE todos ADD COLUMN user_id UUID;
+
+CREATE INDEX idx_user_id ON todos(user_id);
@@ -0,0 +1,3 @@
+ALTER TABLE todos ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_archived ON todos(archived);
@@ -0,0 +1,4 @@
+-- Subtasks point at their parent todo
+ALTER TABLE todos ADD COLUMN parent_id UUID REFERENCES todos(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_parent_id ON todos(parent_id);
@@ -0,0 +1,4 @@
+-- Completing a recurring todo creates its next occurrence
+CREATE TYPE todo_recurrence AS ENUM ('daily', 'weekly', 'monthly');
+
+ALTER TABLE todos ADD COLUMN recurrence todo_recurrence;
@@ -0,0 +1,11 @@
+-- Remembers which todo a create request with an Idempotency-Key produced
+CREATE TABLE idempotency_keys (
+    user_id UUID,
+    key VARCHAR(255) NOT NULL,
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Without a user every key shares the NULL owner
+    CONSTRAINT idempotency_keys_user_key UNIQUE NULLS NOT DISTINCT (user_id, key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
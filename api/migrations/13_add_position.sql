@@ -0,0 +1,12 @@
+-- Manual ordering. Positions are fractional so a move only rewrites the moved todo;
+-- existing todos are spaced out in creation order, per user.
+ALTER TABLE todos ADD COLUMN position DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+UPDATE todos SET position = ranked.rank * 1024
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY created_at, id) AS rank
+    FROM todos
+) AS ranked
+WHERE todos.id = ranked.id;
+
+CREATE INDEX idx_user_position ON todos(user_id, position);
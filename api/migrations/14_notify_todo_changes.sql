@@ -0,0 +1,41 @@
+-- Announce every change to a todo on the todo_changes channel, so each server instance can
+-- pass on changes made through the others to its own live-update subscribers. The payload
+-- carries IDs only, since NOTIFY payloads are capped at 8000 bytes; listeners read the row.
+-- `origin` is the instance that made the change (see `db::instance_id`), NULL from psql.
+CREATE FUNCTION notify_todo_change() RETURNS trigger AS $$
+DECLARE
+    changed todos%ROWTYPE;
+    kind TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+    ELSE
+        changed := NEW;
+    END IF;
+
+    -- Mirrors the events the handlers publish: a soft delete is a delete and a restore
+    -- brings the todo back as created. Changes to already deleted rows are not announced.
+    kind := CASE
+        WHEN TG_OP = 'INSERT' THEN 'created'
+        WHEN TG_OP = 'DELETE' AND OLD.deleted_at IS NULL THEN 'deleted'
+        WHEN TG_OP = 'DELETE' THEN NULL
+        WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'deleted'
+        WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'created'
+        WHEN NEW.deleted_at IS NULL THEN 'updated'
+    END;
+
+    IF kind IS NOT NULL THEN
+        PERFORM pg_notify('todo_changes', json_build_object(
+            'type', kind,
+            'id', changed.id,
+            'user_id', changed.user_id,
+            'origin', current_setting('todo_app.instance', true)
+        )::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_notify_change
+    AFTER INSERT OR UPDATE OR DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
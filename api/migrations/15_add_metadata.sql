@@ -0,0 +1,2 @@
+-- Free-form JSON object clients can keep alongside a todo, e.g. attachment URLs
+ALTER TABLE todos ADD COLUMN metadata JSONB;
@@ -0,0 +1,4 @@
+-- Category color: a lowercase #rrggbb or one of the named colors the API accepts
+ALTER TABLE todos ADD COLUMN color TEXT;
+
+CREATE INDEX idx_user_color ON todos(user_id, color);
@@ -0,0 +1,29 @@
+-- Audit trail: every write that bumps a todo's version (updates, moves, archiving, soft
+-- deletes and restores) first copies the row as it was, with its tags, into todo_history.
+-- The trigger runs inside the writing transaction, so a rolled-back change leaves no entry.
+-- Hard-deleting a todo removes its history with it.
+CREATE TABLE todo_history (
+    id BIGSERIAL PRIMARY KEY,
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    snapshot JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_todo_history_todo_id ON todo_history(todo_id, id);
+
+-- Tags are written after the todos row, so here they are still the old ones
+CREATE FUNCTION record_todo_history() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO todo_history (todo_id, snapshot)
+    VALUES (OLD.id, to_jsonb(OLD) || jsonb_build_object(
+        'tags', ARRAY(SELECT tag FROM todo_tags WHERE todo_id = OLD.id ORDER BY tag)
+    ));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Bookkeeping writes that leave the version alone, such as renumbering positions, are skipped
+CREATE TRIGGER todos_record_history
+    AFTER UPDATE ON todos
+    FOR EACH ROW WHEN (OLD.version IS DISTINCT FROM NEW.version)
+    EXECUTE FUNCTION record_todo_history();
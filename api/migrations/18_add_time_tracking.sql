@@ -0,0 +1,4 @@
+-- Optional time tracking, in whole minutes
+ALTER TABLE todos
+    ADD COLUMN estimated_minutes INTEGER CHECK (estimated_minutes >= 0),
+    ADD COLUMN actual_minutes INTEGER CHECK (actual_minutes >= 0);
@@ -0,0 +1,9 @@
+-- Tags are now stored trimmed and lowercase; bring the existing ones in line, merging
+-- spellings that are now the same tag and dropping blank ones
+INSERT INTO todo_tags (todo_id, tag)
+SELECT todo_id, lower(btrim(tag, E' \t\r\n'))
+FROM todo_tags
+WHERE btrim(tag, E' \t\r\n') <> ''
+ON CONFLICT DO NOTHING;
+
+DELETE FROM todo_tags WHERE tag <> lower(btrim(tag, E' \t\r\n')) OR btrim(tag, E' \t\r\n') = '';
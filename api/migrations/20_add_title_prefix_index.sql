@@ -0,0 +1,3 @@
+-- Prefix lookups for GET /api/todos/autocomplete. text_pattern_ops lets LIKE 'abc%' use the
+-- index whatever the database's collation; lower() makes the match ignore case.
+CREATE INDEX idx_title_prefix ON todos (lower(title) text_pattern_ops) WHERE deleted_at IS NULL;
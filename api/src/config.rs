@@ -0,0 +1,279 @@
+use std::env;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::db::cleanup::DEFAULT_CLEANUP_INTERVAL_SECS;
+use crate::db::{self, Cleanup, PoolSettings};
+use crate::handlers::todo::DEFAULT_IDEMPOTENCY_KEY_TTL_SECS;
+use crate::handlers::{IdempotencyKeyTtl, TodoLimit};
+use crate::logging::LogFormat;
+use crate::middleware::body_log::DEFAULT_LOG_BODIES_MAX_BYTES;
+use crate::middleware::rate_limit::{DEFAULT_RATE_LIMIT_REQUESTS, DEFAULT_RATE_LIMIT_WINDOW_SECS};
+use crate::middleware::timeout::DEFAULT_REQUEST_TIMEOUT_SECS;
+use crate::middleware::{BodyLogging, CorsSettings, RateLimit, RequestTimeout};
+use crate::routes::ApiBasePath;
+
+/// Most workers `HttpServer::workers` accepts
+const MAX_WORKERS: usize = 512;
+
+/// actix-web's own defaults, kept so leaving the settings unset changes nothing
+const DEFAULT_KEEP_ALIVE_SECS: u64 = 5;
+const DEFAULT_CLIENT_TIMEOUT_SECS: u64 = 5;
+
+/// What `number` expects a setting to look like
+const NUMBER: &str = "a non-negative integer";
+
+/// What the server needs to start, read once in `main`
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database_url: String,
+    pub host: String,
+    pub port: u16,
+    /// `WORKERS`, or actix's default of one worker per available CPU
+    pub workers: usize,
+    pub run_migrations: bool,
+    pub log_format: LogFormat,
+    pub cors: CorsSettings,
+    /// How long an idle connection is kept open for the next request; zero disables keep-alive
+    pub keep_alive: Duration,
+    /// How long a client may take to send a request's headers; zero disables the timeout
+    pub client_timeout: Duration,
+    /// Prefix of every route, empty unless `API_BASE_PATH` is set
+    pub api_base_path: ApiBasePath,
+    pub database: PoolSettings,
+    /// `None` when `RATE_LIMIT_REQUESTS` or `RATE_LIMIT_WINDOW_SECS` is 0
+    pub rate_limit: Option<RateLimit>,
+    /// `None` when `REQUEST_TIMEOUT_SECS` is 0
+    pub request_timeout: Option<RequestTimeout>,
+    /// `Some` only when `LOG_BODIES` is set
+    pub body_logging: Option<BodyLogging>,
+    pub idempotency_key_ttl: IdempotencyKeyTtl,
+    /// `Some` only when `MAX_TODOS` is set to a positive number
+    pub todo_limit: Option<TodoLimit>,
+    /// `None` when `CLEANUP_INTERVAL_SECS` is 0
+    pub cleanup: Option<Cleanup>,
+}
+
+/// Every missing or malformed variable `Config::from_env` found
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration ({} problem(s)):", self.0.len())?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Read and check every setting, reporting all problems at once
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut errors = Vec::new();
+
+        let database_url = match env::var("DATABASE_URL") {
+            Ok(url) if !url.trim().is_empty() => url,
+            _ => {
+                errors.push("DATABASE_URL must be set".to_string());
+                String::new()
+            }
+        };
+        let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let port = parse(&mut errors, "PORT", "a port number from 0 to 65535").unwrap_or(8080);
+        let workers = match env::var("WORKERS") {
+            Ok(value) => match value.parse() {
+                Ok(workers) if (1..=MAX_WORKERS).contains(&workers) => workers,
+                _ => {
+                    errors.push(format!(
+                        "WORKERS must be between 1 and {}, got '{}'",
+                        MAX_WORKERS, value
+                    ));
+                    1
+                }
+            },
+            Err(_) => std::thread::available_parallelism().map_or(2, NonZeroUsize::get),
+        };
+        let log_format = match env::var("LOG_FORMAT") {
+            Ok(value) => value.parse().unwrap_or_else(|err: String| {
+                errors.push(err);
+                LogFormat::Text
+            }),
+            Err(_) => LogFormat::Text,
+        };
+
+        let keep_alive = number(&mut errors, "KEEP_ALIVE_SECS", DEFAULT_KEEP_ALIVE_SECS);
+        let client_timeout =
+            number(&mut errors, "CLIENT_TIMEOUT_SECS", DEFAULT_CLIENT_TIMEOUT_SECS);
+
+        let database = pool_settings(&mut errors);
+
+        let rate_limit_requests =
+            number(&mut errors, "RATE_LIMIT_REQUESTS", DEFAULT_RATE_LIMIT_REQUESTS);
+        let rate_limit_window =
+            number(&mut errors, "RATE_LIMIT_WINDOW_SECS", DEFAULT_RATE_LIMIT_WINDOW_SECS);
+        let rate_limit = (rate_limit_requests > 0 && rate_limit_window > 0).then(|| RateLimit {
+            requests: rate_limit_requests,
+            window: Duration::from_secs(rate_limit_window),
+        });
+        let request_timeout =
+            seconds(number(&mut errors, "REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT_SECS))
+                .map(RequestTimeout::new);
+        let log_bodies_max_bytes =
+            number(&mut errors, "LOG_BODIES_MAX_BYTES", DEFAULT_LOG_BODIES_MAX_BYTES);
+        let body_logging = flag("LOG_BODIES").then(|| BodyLogging::new(log_bodies_max_bytes));
+
+        let idempotency_key_ttl = IdempotencyKeyTtl::new(chrono::Duration::seconds(i64::from(
+            number(&mut errors, "IDEMPOTENCY_KEY_TTL_SECS", DEFAULT_IDEMPOTENCY_KEY_TTL_SECS),
+        )));
+        let todo_limit = Some(number(&mut errors, "MAX_TODOS", 0u32))
+            .filter(|&max| max > 0)
+            .map(TodoLimit::new);
+        let cleanup_interval =
+            number(&mut errors, "CLEANUP_INTERVAL_SECS", DEFAULT_CLEANUP_INTERVAL_SECS);
+        // `PURGE_DELETED_AFTER_DAYS` is the name the setting had before the trash existed
+        let purge_deleted_after = parse_either::<u32>(
+            &mut errors,
+            "TRASH_RETENTION_DAYS",
+            "PURGE_DELETED_AFTER_DAYS",
+            NUMBER,
+        )
+        .map(|days| chrono::Duration::days(i64::from(days)));
+        let cleanup = seconds(cleanup_interval).map(|interval| {
+            Cleanup::new(interval, idempotency_key_ttl.duration(), purge_deleted_after)
+        });
+
+        let api_base_path = match env::var("API_BASE_PATH") {
+            Ok(path) => ApiBasePath::parse(&path).unwrap_or_else(|err| {
+                errors.push(err);
+                ApiBasePath::default()
+            }),
+            Err(_) => ApiBasePath::default(),
+        };
+
+        let cors = CorsSettings::from_env();
+        for origin in &cors.allowed_origins {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                errors.push(format!(
+                    "ALLOWED_ORIGINS entries must start with http:// or https://, got '{}'",
+                    origin
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(ConfigError(errors));
+        }
+        Ok(Config {
+            database_url,
+            host,
+            port,
+            workers,
+            run_migrations: flag("RUN_MIGRATIONS"),
+            log_format,
+            cors,
+            keep_alive: Duration::from_secs(keep_alive),
+            client_timeout: Duration::from_secs(client_timeout),
+            api_base_path,
+            database,
+            rate_limit,
+            request_timeout,
+            body_logging,
+            idempotency_key_ttl,
+            todo_limit,
+            cleanup,
+        })
+    }
+
+    /// `host:port` to bind
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// The `DATABASE_*` settings, `SLOW_QUERY_MS` and `DB_SCHEMA`
+fn pool_settings(errors: &mut Vec<String>) -> PoolSettings {
+    let max_connections = number(errors, "DATABASE_MAX_CONNECTIONS", db::DEFAULT_MAX_CONNECTIONS);
+    let min_connections = number(errors, "DATABASE_MIN_CONNECTIONS", db::DEFAULT_MIN_CONNECTIONS);
+    if min_connections > max_connections {
+        errors.push(format!(
+            "DATABASE_MIN_CONNECTIONS ({}) cannot exceed DATABASE_MAX_CONNECTIONS ({})",
+            min_connections, max_connections
+        ));
+    }
+    let acquire_timeout =
+        number(errors, "DATABASE_ACQUIRE_TIMEOUT_SECS", db::DEFAULT_ACQUIRE_TIMEOUT_SECS);
+    // 0 keeps connections open for as long as they are in use or idle, respectively. Both are
+    // also read without the `_SECS` suffix, still in seconds.
+    let max_lifetime =
+        parse_either(errors, "DATABASE_MAX_LIFETIME_SECS", "DATABASE_MAX_LIFETIME", NUMBER)
+            .unwrap_or(db::DEFAULT_MAX_LIFETIME_SECS);
+    let idle_timeout =
+        parse_either(errors, "DATABASE_IDLE_TIMEOUT_SECS", "DATABASE_IDLE_TIMEOUT", NUMBER)
+            .unwrap_or(db::DEFAULT_IDLE_TIMEOUT_SECS);
+    let connect_attempts =
+        number(errors, "DATABASE_CONNECT_ATTEMPTS", db::DEFAULT_CONNECT_ATTEMPTS);
+    let connect_retry_delay_ms =
+        number(errors, "DATABASE_CONNECT_RETRY_DELAY_MS", db::DEFAULT_CONNECT_RETRY_DELAY_MS);
+    let slow_query_ms = number(errors, "SLOW_QUERY_MS", db::DEFAULT_SLOW_QUERY_MS);
+
+    PoolSettings {
+        max_connections,
+        min_connections,
+        acquire_timeout: Duration::from_secs(acquire_timeout),
+        max_lifetime: seconds(max_lifetime),
+        idle_timeout: seconds(idle_timeout),
+        connect_attempts: connect_attempts.max(1),
+        connect_retry_delay: Duration::from_millis(connect_retry_delay_ms),
+        slow_query: (slow_query_ms > 0).then(|| Duration::from_millis(slow_query_ms)),
+        schema: env::var("DB_SCHEMA").ok().filter(|schema| !schema.trim().is_empty()),
+    }
+}
+
+/// A numeric setting, or `default` when it is unset or malformed
+fn number<T: FromStr>(errors: &mut Vec<String>, key: &str, default: T) -> T {
+    parse(errors, key, NUMBER).unwrap_or(default)
+}
+
+/// A duration in seconds; `None` for 0, which the settings use to turn a feature off
+fn seconds(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Parse `key` when it is set, recording a problem if it doesn't parse as `expected`
+fn parse<T: FromStr>(errors: &mut Vec<String>, key: &str, expected: &str) -> Option<T> {
+    let value = env::var(key).ok()?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(format!("{} must be {}, got '{}'", key, expected, value));
+            None
+        }
+    }
+}
+
+/// Parse a setting that can be given under either of two names, recording a problem if both
+/// are set, since which one wins would otherwise be a guess
+fn parse_either<T: FromStr>(
+    errors: &mut Vec<String>,
+    key: &str,
+    alias: &str,
+    expected: &str,
+) -> Option<T> {
+    if env::var(key).is_ok() && env::var(alias).is_ok() {
+        errors.push(format!("{} and {} are the same setting; set only one of them", key, alias));
+    }
+    parse(errors, key, expected).or_else(|| parse(errors, alias, expected))
+}
+
+/// A boolean setting; `1`, `true` and `yes` turn it on
+fn flag(key: &str) -> bool {
+    env::var(key)
+        .map(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
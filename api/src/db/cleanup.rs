@@ -0,0 +1,112 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+pub(crate) const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Names the advisory lock that lets one instance at a time run the cleanup
+const LOCK_NAME: &str = "todo_app.cleanup";
+
+/// Periodic removal of rows nothing reads any more: expired idempotency keys and, when
+/// `TRASH_RETENTION_DAYS` is set, todos soft-deleted longer ago than that
+#[derive(Debug, Clone, Copy)]
+pub struct Cleanup {
+    interval: Duration,
+    idempotency_ttl: chrono::Duration,
+    purge_deleted_after: Option<chrono::Duration>,
+}
+
+/// What one cleanup pass removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub idempotency_keys: u64,
+    pub todos: u64,
+}
+
+impl Cleanup {
+    pub fn new(
+        interval: Duration,
+        idempotency_ttl: chrono::Duration,
+        purge_deleted_after: Option<chrono::Duration>,
+    ) -> Self {
+        Self { interval, idempotency_ttl, purge_deleted_after }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn purge_deleted_after(&self) -> Option<chrono::Duration> {
+        self.purge_deleted_after
+    }
+
+    /// Run one pass unless another instance is running one; `None` means it was.
+    ///
+    /// The lock is transaction-scoped, so it is released with the commit, or with the
+    /// connection if this instance dies mid-pass.
+    pub async fn run_once(&self, pool: &PgPool) -> Result<Option<CleanupReport>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let locked: bool = sqlx::query_scalar(
+            "SELECT pg_try_advisory_xact_lock(hashtextextended($1, 0))"
+        )
+        .bind(LOCK_NAME)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !locked {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let idempotency_keys = sqlx::query("DELETE FROM idempotency_keys WHERE created_at <= $1")
+            .bind(now - self.idempotency_ttl)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let mut todos = 0;
+        if let Some(after) = self.purge_deleted_after {
+            // Deleting a parent cascades to its subtasks, so keep any that has one still live
+            // or still inside the retention window; it goes in a later pass, with them
+            todos = sqlx::query(
+                "DELETE FROM todos
+                 WHERE deleted_at <= $1
+                   AND NOT EXISTS (
+                       SELECT 1 FROM todos AS child
+                       WHERE child.parent_id = todos.id
+                         AND (child.deleted_at IS NULL OR child.deleted_at > $1)
+                   )"
+            )
+            .bind(now - after)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(Some(CleanupReport { idempotency_keys, todos }))
+    }
+}
+
+/// Run the cleanup every `interval`, starting now. Every instance runs this loop, but the
+/// advisory lock makes the others skip a pass while one is running. Runs until the process
+/// exits.
+pub async fn run_cleanup(pool: PgPool, cleanup: Cleanup) {
+    let mut ticks = tokio::time::interval(cleanup.interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        match cleanup.run_once(&pool).await {
+            Ok(Some(CleanupReport { idempotency_keys: 0, todos: 0 })) => {
+                log::debug!("Cleanup found nothing to remove")
+            }
+            Ok(Some(report)) => log::info!(
+                "Cleanup removed {} expired idempotency keys and {} deleted todos",
+                report.idempotency_keys,
+                report.todos
+            ),
+            Ok(None) => log::debug!("Skipping cleanup, another instance is running it"),
+            Err(err) => log::error!("Cleanup failed: {}", err),
+        }
+    }
+}
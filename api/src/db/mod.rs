@@ -1,15 +1,188 @@
-use sqlx::postgres::PgPoolOptions;
-use sqlx::PgPool;
-use std::env;
+pub mod cleanup;
 
-pub async fn establish_connection() -> Result<PgPool, sqlx::Error> {
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
+pub use cleanup::{run_cleanup, Cleanup, CleanupReport};
 
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await?;
+use sqlx::migrate::MigrateError;
+use log::LevelFilter;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Executor, PgPool};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use uuid::Uuid;
 
-    Ok(pool)
+pub(crate) const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+pub(crate) const DEFAULT_MIN_CONNECTIONS: u32 = 0;
+pub(crate) const DEFAULT_ACQUIRE_TIMEOUT_SECS: u64 = 5;
+pub(crate) const DEFAULT_MAX_LIFETIME_SECS: u64 = 30 * 60;
+pub(crate) const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 10 * 60;
+pub(crate) const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+pub(crate) const DEFAULT_CONNECT_RETRY_DELAY_MS: u64 = 500;
+/// sqlx's own threshold for warning about a slow statement
+pub(crate) const DEFAULT_SLOW_QUERY_MS: u64 = 1000;
+/// Longest wait between two connection attempts, however many have failed
+const MAX_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// SQLSTATE Postgres answers with while it is still starting up
+const CANNOT_CONNECT_NOW: &str = "57P03";
+
+/// How `establish_connection` sizes, maintains and retries the pool; read by `Config::from_env`
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    /// `None` keeps connections open for as long as they are in use or idle, respectively
+    pub max_lifetime: Option<Duration>,
+    pub idle_timeout: Option<Duration>,
+    /// At least 1
+    pub connect_attempts: u32,
+    pub connect_retry_delay: Duration,
+    /// Queries slower than this are logged as warnings; `None` turns the warnings off
+    pub slow_query: Option<Duration>,
+    /// Schema put on every connection's `search_path`
+    pub schema: Option<String>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_connections: DEFAULT_MIN_CONNECTIONS,
+            acquire_timeout: Duration::from_secs(DEFAULT_ACQUIRE_TIMEOUT_SECS),
+            max_lifetime: Some(Duration::from_secs(DEFAULT_MAX_LIFETIME_SECS)),
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS)),
+            connect_attempts: DEFAULT_CONNECT_ATTEMPTS,
+            connect_retry_delay: Duration::from_millis(DEFAULT_CONNECT_RETRY_DELAY_MS),
+            slow_query: Some(Duration::from_millis(DEFAULT_SLOW_QUERY_MS)),
+            schema: None,
+        }
+    }
+}
+
+/// Whether a failed connection might succeed later, e.g. because the server is not up yet.
+/// Bad credentials or a missing database will not fix themselves, so those fail at once.
+fn is_transient(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(db_err) => db_err.code().as_deref() == Some(CANNOT_CONNECT_NOW),
+        _ => false,
+    }
+}
+
+/// Identifies this process's database sessions. Every pooled connection stores it in the
+/// `todo_app.instance` setting, which the change trigger copies into its notifications so
+/// `events::relay_notifications` can skip the changes this process has published already.
+pub fn instance_id() -> Uuid {
+    static INSTANCE_ID: OnceLock<Uuid> = OnceLock::new();
+    *INSTANCE_ID.get_or_init(Uuid::new_v4)
+}
+
+/// Quote a schema name for use in SQL, so any name Postgres allows can be configured
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Connect, retrying transient failures with exponential backoff
+pub async fn establish_connection(
+    database_url: &str,
+    settings: &PoolSettings,
+) -> Result<PgPool, sqlx::Error> {
+    let secs = |timeout: Option<Duration>| timeout.map_or(0, |timeout| timeout.as_secs());
+    log::info!(
+        "Database pool: max {} / min {} connections, {}s acquire timeout, \
+         {}s max lifetime, {}s idle timeout",
+        settings.max_connections,
+        settings.min_connections,
+        settings.acquire_timeout.as_secs(),
+        secs(settings.max_lifetime),
+        secs(settings.idle_timeout)
+    );
+    if let Some(slow_query) = settings.slow_query {
+        log::info!("Logging queries slower than {}ms", slow_query.as_millis());
+    }
+
+    let mut session = vec![format!("SET todo_app.instance TO '{}'", instance_id())];
+    // Point every pooled connection at DB_SCHEMA, so neither the queries nor the migrations
+    // have to name it. `public` is left off the path so a missing table there can't be
+    // picked up by mistake.
+    if let Some(schema) = &settings.schema {
+        log::info!("Database schema: {}", schema);
+        session.push(format!("SET search_path TO {}", quote_identifier(schema)));
+    }
+    let session = session.join("; ");
+
+    // sqlx times every statement itself; one over the threshold is logged as a warning on the
+    // `sqlx::query` target with its elapsed time, a summary and the full SQL
+    let (slow_level, slow_threshold) = match settings.slow_query {
+        Some(threshold) => (LevelFilter::Warn, threshold),
+        None => (LevelFilter::Off, Duration::default()),
+    };
+    let connect_options = PgConnectOptions::from_str(database_url)?
+        .log_slow_statements(slow_level, slow_threshold);
+
+    let options = PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .max_lifetime(settings.max_lifetime)
+        .idle_timeout(settings.idle_timeout)
+        .after_connect(move |conn, _meta| {
+            let session = session.clone();
+            Box::pin(async move {
+                conn.execute(session.as_str()).await?;
+                Ok(())
+            })
+        });
+
+    let attempts = settings.connect_attempts.max(1);
+    let retry_delay = settings.connect_retry_delay;
+    let mut attempt = 1;
+    loop {
+        match options.clone().connect_with(connect_options.clone()).await {
+            Ok(pool) => return Ok(pool),
+            Err(err) if attempt < attempts && is_transient(&err) => {
+                let delay = retry_delay
+                    .saturating_mul(2u32.saturating_pow(attempt - 1))
+                    .min(MAX_CONNECT_RETRY_DELAY);
+                log::warn!(
+                    "Database connection attempt {}/{} failed: {}; retrying in {}ms",
+                    attempt,
+                    attempts,
+                    err,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(err) => {
+                log::error!("Database connection attempt {}/{} failed: {}", attempt, attempts, err);
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Apply any migrations from `migrations/` the database has not seen yet, logging each one
+pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    let migrator = sqlx::migrate!("./migrations");
+
+    // The bookkeeping table only exists once sqlx has migrated this database before
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations")
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+
+    migrator.run(pool).await?;
+
+    let mut count = 0;
+    for migration in migrator.iter().filter(|m| !applied.contains(&m.version)) {
+        log::info!("Applied migration {} ({})", migration.version, migration.description);
+        count += 1;
+    }
+    if count == 0 {
+        log::info!("Database schema is up to date");
+    }
+
+    Ok(())
 }
@@ -1,20 +1,73 @@
-use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use actix_web::{
+    error::{JsonPayloadError, PathError, ResponseError},
+    http::{header::RETRY_AFTER, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
 use serde::Serialize;
 use std::fmt;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+/// Seconds a client is told to wait before retrying a `503`. Connections are held for a
+/// single query or transaction, so one usually frees up well within this.
+const RETRY_AFTER_SECS: u32 = 1;
+
+/// Largest JSON body accepted by any handler
+pub const MAX_JSON_PAYLOAD_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+/// A single problem with one field of a request body
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects every field error in a request so they can be reported together
+#[derive(Debug, Default)]
+pub struct ValidationErrors(Vec<FieldError>);
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// `Ok` when nothing was collected, otherwise an `ApiError::Validation`
+    pub fn into_result(self) -> Result<(), ApiError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(self.0))
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ApiError {
     NotFound(String),
     BadRequest(String),
+    Unauthorized(String),
+    Validation(Vec<FieldError>),
     InternalServerError(String),
-    #[allow(dead_code)]
+    ServiceUnavailable(String),
     Conflict(String),
+    PreconditionFailed(String),
+    PayloadTooLarge(String),
+    TooManyRequests(String),
+    GatewayTimeout(String),
 }
 
 impl fmt::Display for ApiError {
@@ -22,8 +75,17 @@ impl fmt::Display for ApiError {
         match self {
             ApiError::NotFound(msg) => write!(f, "{}", msg),
             ApiError::BadRequest(msg) => write!(f, "{}", msg),
+            ApiError::Unauthorized(msg) => write!(f, "{}", msg),
+            ApiError::Validation(fields) => {
+                write!(f, "Validation failed for {} field(s)", fields.len())
+            }
             ApiError::InternalServerError(msg) => write!(f, "{}", msg),
+            ApiError::ServiceUnavailable(msg) => write!(f, "{}", msg),
             ApiError::Conflict(msg) => write!(f, "{}", msg),
+            ApiError::PreconditionFailed(msg) => write!(f, "{}", msg),
+            ApiError::PayloadTooLarge(msg) => write!(f, "{}", msg),
+            ApiError::TooManyRequests(msg) => write!(f, "{}", msg),
+            ApiError::GatewayTimeout(msg) => write!(f, "{}", msg),
         }
     }
 }
@@ -33,8 +95,15 @@ impl ResponseError for ApiError {
         match self {
             ApiError::NotFound(_) => StatusCode::NOT_FOUND,
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
             ApiError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::GatewayTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
         }
     }
 
@@ -42,16 +111,34 @@ impl ResponseError for ApiError {
         let error_type = match self {
             ApiError::NotFound(_) => "NOT_FOUND",
             ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Validation(_) => "VALIDATION",
             ApiError::InternalServerError(_) => "INTERNAL_SERVER_ERROR",
+            ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
             ApiError::Conflict(_) => "CONFLICT",
+            ApiError::PreconditionFailed(_) => "PRECONDITION_FAILED",
+            ApiError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            ApiError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
+            ApiError::GatewayTimeout(_) => "GATEWAY_TIMEOUT",
+        };
+
+        let fields = match self {
+            ApiError::Validation(fields) => fields.clone(),
+            _ => Vec::new(),
         };
 
         let response = ErrorResponse {
             error: error_type.to_string(),
             message: self.to_string(),
+            fields,
         };
 
-        HttpResponse::build(self.status_code()).json(response)
+        let mut builder = HttpResponse::build(self.status_code());
+        // Overload passes, so tell clients when to come back rather than to give up
+        if let ApiError::ServiceUnavailable(_) = self {
+            builder.insert_header((RETRY_AFTER, RETRY_AFTER_SECS));
+        }
+        builder.json(response)
     }
 }
 
@@ -61,7 +148,57 @@ impl From<sqlx::Error> for ApiError {
             sqlx::Error::RowNotFound => {
                 ApiError::NotFound("Resource not found".to_string())
             }
+            // Every connection stayed busy for DATABASE_ACQUIRE_TIMEOUT_SECS: the server is
+            // overloaded, not broken, so this is a 503 rather than a 500
+            sqlx::Error::PoolTimedOut => {
+                log::warn!("Database pool exhausted: no connection became free in time");
+                ApiError::ServiceUnavailable(
+                    "All database connections are busy; retry shortly".to_string(),
+                )
+            }
+            sqlx::Error::PoolClosed => {
+                ApiError::ServiceUnavailable("The server is shutting down".to_string())
+            }
             _ => ApiError::InternalServerError(format!("Database error: {}", err)),
         }
     }
 }
+
+/// How every handler reads JSON bodies: up to `MAX_JSON_PAYLOAD_BYTES`, with failures
+/// reported by `json_error_handler`
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(MAX_JSON_PAYLOAD_BYTES)
+        .error_handler(json_error_handler)
+}
+
+/// Error handler for `web::JsonConfig` so body extraction failures use our error shape
+/// instead of actix's plain-text responses
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::OverflowKnownLength { limit, .. }
+        | JsonPayloadError::Overflow { limit } => {
+            ApiError::PayloadTooLarge(format!("Request body exceeds the {} byte limit", limit))
+                .into()
+        }
+        // serde_json's message names the problem and where it is, e.g. "invalid type:
+        // string \"yes\", expected a boolean at line 1 column 18"
+        JsonPayloadError::Deserialize(err) if err.is_data() => {
+            ApiError::BadRequest(format!("Invalid request body: {}", err)).into()
+        }
+        JsonPayloadError::Deserialize(err) => {
+            ApiError::BadRequest(format!("Request body is not valid JSON: {}", err)).into()
+        }
+        JsonPayloadError::ContentType => {
+            ApiError::BadRequest("Content-Type must be application/json".to_string()).into()
+        }
+        // The body could not be read, e.g. the client went away or sent a bad encoding
+        err => ApiError::BadRequest(format!("Could not read the request body: {}", err)).into(),
+    }
+}
+
+/// Error handler for `web::PathConfig`; the only path parameters are todo IDs,
+/// so any extraction failure means the ID was not a UUID
+pub fn path_error_handler(_err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest("Invalid todo ID format".to_string()).into()
+}
@@ -0,0 +1,144 @@
+pub mod relay;
+
+pub use relay::relay_notifications;
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::middleware::CurrentUser;
+use crate::models::TodoResponse;
+
+/// How far a slow subscriber may fall behind before it starts missing events
+const EVENT_BUFFER: usize = 256;
+
+/// A change to a todo, as pushed to live-update subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TodoEvent {
+    Created { todo: TodoResponse },
+    Updated { todo: TodoResponse },
+    Deleted { id: Uuid },
+    /// The server is shutting down; the subscription ends after this, so reconnect
+    Shutdown,
+}
+
+impl TodoEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TodoEvent::Created { .. } => "created",
+            TodoEvent::Updated { .. } => "updated",
+            TodoEvent::Deleted { .. } => "deleted",
+            TodoEvent::Shutdown => "shutdown",
+        }
+    }
+
+    /// The event's data without the type tag: the todo, `{"id": ...}` for deletes and `{}`
+    /// for shutdowns
+    pub fn data(&self) -> serde_json::Result<String> {
+        match self {
+            TodoEvent::Created { todo } | TodoEvent::Updated { todo } => {
+                serde_json::to_string(todo)
+            }
+            TodoEvent::Deleted { id } => serde_json::to_string(&serde_json::json!({ "id": id })),
+            TodoEvent::Shutdown => Ok("{}".to_string()),
+        }
+    }
+}
+
+/// An event together with the owner of the todo it concerns
+#[derive(Debug, Clone)]
+struct Envelope {
+    user_id: Option<Uuid>,
+    event: TodoEvent,
+}
+
+/// In-process fan-out of todo changes; each running server has its own, and
+/// `relay_notifications` feeds it the changes made through the others
+pub struct EventBus {
+    sender: broadcast::Sender<Envelope>,
+    shut_down: AtomicBool,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender, shut_down: AtomicBool::new(false) }
+    }
+
+    /// Notify subscribers acting as `user`; having none is not an error
+    pub fn publish(&self, user: CurrentUser, event: TodoEvent) {
+        let _ = self.sender.send(Envelope { user_id: user.0, event });
+    }
+
+    /// Receive events about `user`'s todos from now on
+    pub fn subscribe(&self, user: CurrentUser) -> Subscription {
+        let state = if self.shut_down.load(Ordering::SeqCst) {
+            SubscriptionState::ShuttingDown
+        } else {
+            SubscriptionState::Open
+        };
+        Subscription {
+            receiver: self.sender.subscribe(),
+            user_id: user.0,
+            state,
+        }
+    }
+
+    /// Send every subscriber, whoever it acts as, a final `Shutdown` event that ends its
+    /// subscription. Subscribing afterwards yields only that event.
+    pub fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+        let _ = self.sender.send(Envelope { user_id: None, event: TodoEvent::Shutdown });
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubscriptionState {
+    Open,
+    /// Subscribed after `EventBus::shutdown`; the `Shutdown` event is still to be delivered
+    ShuttingDown,
+    Closed,
+}
+
+pub struct Subscription {
+    receiver: broadcast::Receiver<Envelope>,
+    user_id: Option<Uuid>,
+    state: SubscriptionState,
+}
+
+impl Subscription {
+    /// The next event for this subscriber, or `None` once the bus is gone or has shut down.
+    /// Events missed by falling behind are skipped rather than ending the subscription.
+    pub async fn recv(&mut self) -> Option<TodoEvent> {
+        match self.state {
+            SubscriptionState::Open => {}
+            SubscriptionState::ShuttingDown => {
+                self.state = SubscriptionState::Closed;
+                return Some(TodoEvent::Shutdown);
+            }
+            SubscriptionState::Closed => return None,
+        }
+        loop {
+            match self.receiver.recv().await {
+                Ok(Envelope { event: TodoEvent::Shutdown, .. }) => {
+                    self.state = SubscriptionState::Closed;
+                    return Some(TodoEvent::Shutdown);
+                }
+                Ok(envelope) if envelope.user_id == self.user_id => return Some(envelope.event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Event subscriber fell behind and missed {} events", missed);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
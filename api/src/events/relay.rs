@@ -0,0 +1,94 @@
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{EventBus, TodoEvent};
+use crate::db;
+use crate::handlers::todo::TODO_COLUMNS;
+use crate::middleware::CurrentUser;
+use crate::models::Todo;
+
+/// Channel the `notify_todo_change` trigger announces changes on
+const CHANNEL: &str = "todo_changes";
+
+/// Pause before listening again after the listener connection fails
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A `todo_changes` payload, as built by the trigger
+#[derive(Debug, Deserialize)]
+struct Notification {
+    #[serde(rename = "type")]
+    kind: String,
+    id: Uuid,
+    user_id: Option<Uuid>,
+    origin: Option<Uuid>,
+}
+
+/// Publish changes made through other server instances (or straight in the database) on the
+/// local bus, so live updates work behind a load balancer. Changes this process made are
+/// skipped; its handlers have published those already. Runs until the process exits.
+pub async fn relay_notifications(pool: PgPool, bus: Arc<EventBus>) {
+    loop {
+        if let Err(err) = listen(&pool, &bus).await {
+            log::error!(
+                "Listening for todo changes failed: {}; retrying in {}s",
+                err,
+                RETRY_DELAY.as_secs()
+            );
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn listen(pool: &PgPool, bus: &EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+    log::info!("Listening for todo changes from other instances");
+
+    let own = db::instance_id();
+    loop {
+        // Reconnects by itself; changes made while it was reconnecting are lost
+        let notification = listener.recv().await?;
+        let notification: Notification = match serde_json::from_str(notification.payload()) {
+            Ok(notification) => notification,
+            Err(err) => {
+                log::warn!("Ignoring malformed todo change notification: {}", err);
+                continue;
+            }
+        };
+        if notification.origin == Some(own) {
+            continue;
+        }
+
+        let user = CurrentUser(notification.user_id);
+        if notification.kind == "deleted" {
+            bus.publish(user, TodoEvent::Deleted { id: notification.id });
+            continue;
+        }
+        // The row may have changed again, or gone, since the notification was sent
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            "SELECT {} FROM todos WHERE id = $1 AND deleted_at IS NULL",
+            TODO_COLUMNS
+        ))
+        .bind(notification.id)
+        .fetch_optional(pool)
+        .await;
+        let todo = match todo {
+            Ok(Some(todo)) => todo,
+            Ok(None) => continue,
+            // Dropping one event beats missing everything while the listener reconnects
+            Err(err) => {
+                log::warn!("Failed to load changed todo {}: {}", notification.id, err);
+                continue;
+            }
+        };
+        let event = match notification.kind.as_str() {
+            "created" => TodoEvent::Created { todo: todo.into() },
+            _ => TodoEvent::Updated { todo: todo.into() },
+        };
+        bus.publish(user, event);
+    }
+}
@@ -0,0 +1,116 @@
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message};
+use futures_util::stream;
+use std::time::Duration;
+
+use crate::error::ApiError;
+use crate::events::{EventBus, TodoEvent};
+use crate::middleware::CurrentUser;
+
+/// How often an idle event stream sends a comment so proxies don't drop the connection
+const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Upgrade to a WebSocket that receives a JSON message for every change to the caller's todos
+#[utoipa::path(
+    get,
+    path = "/api/todos/events",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    responses(
+        (status = 101, description = "Switching to a WebSocket of `TodoEvent` JSON messages"),
+        (status = 400, description = "Not a WebSocket upgrade request", body = ErrorResponse),
+    )
+)]
+pub async fn todo_events(
+    req: HttpRequest,
+    body: web::Payload,
+    bus: web::Data<EventBus>,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)
+        .map_err(|err| ApiError::BadRequest(format!("WebSocket handshake failed: {}", err)))?;
+    let mut events = bus.subscribe(user);
+
+    actix_web::rt::spawn(async move {
+        let reason = loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else { break None };
+                    let json = match serde_json::to_string(&event) {
+                        Ok(json) => json,
+                        Err(err) => {
+                            log::error!("Failed to serialize todo event: {}", err);
+                            continue;
+                        }
+                    };
+                    if session.text(json).await.is_err() {
+                        return;
+                    }
+                    if matches!(event, TodoEvent::Shutdown) {
+                        break Some(CloseReason {
+                            code: CloseCode::Away,
+                            description: Some("Server shutting down".to_string()),
+                        });
+                    }
+                }
+                message = messages.recv() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    // Anything else the client sends is ignored; the socket is push-only
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break None,
+                    Some(Ok(_)) => {}
+                },
+            }
+        };
+        let _ = session.close(reason).await;
+    });
+
+    Ok(response)
+}
+
+/// Hold the connection open as a `text/event-stream` with one event per change to the
+/// caller's todos, named `created`, `updated` or `deleted`, until a final `shutdown` event
+#[utoipa::path(
+    get,
+    path = "/api/todos/stream",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    responses(
+        (
+            status = 200,
+            description = "Server-sent events; `data` is the todo, or `{\"id\"}` for deletes",
+            content_type = "text/event-stream"
+        ),
+    )
+)]
+pub async fn todo_stream(bus: web::Data<EventBus>, user: CurrentUser) -> HttpResponse {
+    let events = bus.subscribe(user);
+    let keep_alive = tokio::time::interval(SSE_KEEP_ALIVE);
+
+    let body = stream::unfold((events, keep_alive), |(mut events, mut keep_alive)| async move {
+        let chunk = loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let event = event?;
+                    match event.data() {
+                        Ok(data) => break format!("event: {}\ndata: {}\n\n", event.name(), data),
+                        Err(err) => log::error!("Failed to serialize todo event: {}", err),
+                    }
+                }
+                _ = keep_alive.tick() => break ": keep-alive\n\n".to_string(),
+            }
+        };
+        Some((Ok::<_, actix_web::Error>(web::Bytes::from(chunk)), (events, keep_alive)))
+    });
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        // Stop nginx from buffering the stream
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body)
+}
@@ -0,0 +1,95 @@
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType, CONTENT_TYPE};
+use actix_web::{error::ErrorInternalServerError, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::middleware::CurrentUser;
+
+const CSV_HEADER: &str = "id,title,description,completed,created_at,updated_at\r\n";
+
+/// Rows fetched ahead of the client before the query waits for it to catch up
+const EXPORT_BUFFER_ROWS: usize = 64;
+
+type ExportRow = (Uuid, String, Option<String>, bool, DateTime<Utc>, DateTime<Utc>);
+
+/// Quote a field when it contains a delimiter, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_line((id, title, description, completed, created_at, updated_at): ExportRow) -> String {
+    format!(
+        "{},{},{},{},{},{}\r\n",
+        id,
+        csv_field(&title),
+        csv_field(description.as_deref().unwrap_or_default()),
+        completed,
+        created_at.to_rfc3339(),
+        updated_at.to_rfc3339()
+    )
+}
+
+/// Download the caller's todos as CSV, streamed row by row as the query yields them
+#[utoipa::path(
+    get,
+    path = "/api/todos/export.csv",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "All live todos as CSV", content_type = "text/csv"),
+    )
+)]
+pub async fn export_csv(pool: web::Data<PgPool>, user: CurrentUser) -> HttpResponse {
+    let pool = pool.into_inner();
+    let (sender, receiver) = mpsc::channel::<Result<String, actix_web::Error>>(EXPORT_BUFFER_ROWS);
+
+    // The row stream borrows the pool, so it runs in its own task and hands lines over
+    actix_web::rt::spawn(async move {
+        let mut rows = sqlx::query_as::<_, ExportRow>(
+            "SELECT id, title, description, completed, created_at, updated_at
+             FROM todos
+             WHERE deleted_at IS NULL AND user_id IS NOT DISTINCT FROM $1
+             ORDER BY created_at, id"
+        )
+        .bind(user.0)
+        .fetch(pool.as_ref());
+
+        loop {
+            let line = match rows.try_next().await {
+                Ok(Some(row)) => Ok(csv_line(row)),
+                Ok(None) => break,
+                Err(err) => {
+                    // Failing the body aborts the download instead of ending it cleanly
+                    log::error!("CSV export failed: {}", err);
+                    Err(ErrorInternalServerError("CSV export failed"))
+                }
+            };
+            let failed = line.is_err();
+            if sender.send(line).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let rows = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|line| (line, receiver))
+    });
+    let body = stream::once(async { Ok(CSV_HEADER.to_string()) })
+        .chain(rows)
+        .map_ok(web::Bytes::from);
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/csv; charset=utf-8"))
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("todos.csv".to_string())],
+        })
+        .streaming(body)
+}
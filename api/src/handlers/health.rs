@@ -0,0 +1,34 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::models::HealthResponse;
+
+/// How long the database gets to answer before the probe reports degraded
+const DB_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Readiness probe: verifies the database answers a trivial query
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "Database reachable", body = HealthResponse),
+        (status = 503, description = "Database unreachable or too slow", body = HealthResponse),
+    )
+)]
+pub async fn health_check(pool: web::Data<PgPool>) -> HttpResponse {
+    let check = sqlx::query("SELECT 1").execute(pool.get_ref());
+
+    match tokio::time::timeout(DB_CHECK_TIMEOUT, check).await {
+        Ok(Ok(_)) => HttpResponse::Ok().json(HealthResponse { status: "ok" }),
+        Ok(Err(err)) => {
+            log::warn!("Health check failed: {}", err);
+            HttpResponse::ServiceUnavailable().json(HealthResponse { status: "degraded" })
+        }
+        Err(_) => {
+            log::warn!("Health check timed out after {:?}", DB_CHECK_TIMEOUT);
+            HttpResponse::ServiceUnavailable().json(HealthResponse { status: "degraded" })
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use actix_web::{web, HttpResponse};
+use prometheus::TEXT_FORMAT;
+use sqlx::PgPool;
+
+use crate::middleware::Metrics;
+
+/// Prometheus scrape endpoint
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String,
+            content_type = "text/plain"),
+    )
+)]
+pub async fn metrics(metrics: web::Data<Metrics>, pool: web::Data<PgPool>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TEXT_FORMAT)
+        .body(metrics.render(pool.get_ref()))
+}
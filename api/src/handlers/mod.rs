@@ -1,5 +1,19 @@
+pub mod events;
+pub mod export;
+pub mod health;
+pub mod metrics;
+pub mod not_found;
 pub mod todo;
 
+pub use events::{todo_events, todo_stream};
+pub use export::export_csv;
+pub use health::health_check;
+pub use metrics::metrics;
+pub use not_found::route_not_found;
 pub use todo::{
-    list_todos, get_todo, create_todo, update_todo, delete_todo,
+    CompletedLock, IdempotencyKeyTtl, TodoLimit, list_todos, todo_stats, todo_board, list_todo_ids,
+    get_todo, list_trash, autocomplete_todos, create_todo, batch_create_todos, import_todos,
+    update_todo, patch_todo, toggle_todo, move_todo, reorder_todos, todo_history, delete_todo,
+    list_subtasks, duplicate_todo, restore_todo, archive_todo, unarchive_todo, batch_delete_todos,
+    purge_completed_todos, complete_all_todos, incomplete_all_todos,
 };
@@ -0,0 +1,9 @@
+use actix_web::HttpResponse;
+
+use crate::error::ApiError;
+
+/// Fallback for requests no route matches, so they get the usual JSON error body
+/// instead of actix's empty 404
+pub async fn route_not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound("Route not found".to_string()))
+}
@@ -1,126 +1,2513 @@
-use actix_web::{web, HttpResponse};
-use sqlx::PgPool;
+use actix_web::http::header::{
+    Accept, ContentType, EntityTag, HeaderName, IfMatch, IfNoneMatch, ETAG, LINK, LOCATION, VARY,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use std::collections::{HashMap, HashSet};
+use std::env;
 
-use crate::models::{CreateTodoRequest, UpdateTodoRequest, TodoResponse, Todo};
-use crate::error::ApiError;
+use crate::models::{
+    CreateTodoRequest, CreateParams, NewTodo, ReplaceTodoRequest, UpdateTodoRequest, TodoUpdate, TodoResponse,
+    Todo, TodoFilter, BatchDeleteRequest, BatchDeleteResponse, DeleteParams, PaginationParams,
+    link_header, cursor_link_header, Cursor, SortParams, TodoStats, ImportTodoRequest,
+    ImportedTodo, ImportParams, ImportResponse, OnConflict, MoveTodoRequest, CompleteAllResponse,
+    OutputParams, DryRunParams, TodoBoard, DuplicateParams, TodoStamp, PurgeCompletedResponse,
+    decode_snapshot, history_entries, ReorderTodosRequest, Unlisted, AutocompleteParams,
+    TodoSuggestion,
+};
+use crate::error::{ApiError, FieldError};
+use crate::models::todo::{SortField, SortOrder};
+use crate::events::{EventBus, TodoEvent};
+use crate::middleware::CurrentUser;
+use crate::routes::ApiBasePath;
 
-/// List all todos
-pub async fn list_todos(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
-    let todos = sqlx::query_as::<_, Todo>(
-        "SELECT id, title, description, completed, created_at, updated_at FROM todos ORDER BY created_at DESC"
+/// Columns selected into `Todo`, in struct order
+pub(crate) const TODO_COLUMNS: &str = "id, title, description, completed, completed_at, due_date, priority, \
+    recurrence, metadata, color, estimated_minutes, actual_minutes, created_at, updated_at, \
+    deleted_at, archived, position, version, \
+    ARRAY(SELECT tag FROM todo_tags WHERE todo_id = todos.id ORDER BY tag) AS tags, \
+    parent_id, (SELECT COUNT(*) FROM todos AS child \
+     WHERE child.parent_id = todos.id AND child.deleted_at IS NULL) AS child_count";
+
+/// How far `X-Sync-Watermark` is set back from the time of the read. Writes stamp
+/// `updated_at` before they commit, and one still open when the list is read must not fall
+/// behind the watermark. Request handlers are cut off after `REQUEST_TIMEOUT_SECS` (30 by
+/// default), so this leaves twice as long.
+const SYNC_WATERMARK_OVERLAP_SECS: i64 = 60;
+
+/// Append the WHERE clause shared by the list and count queries.
+/// Todos owned by other users are always excluded. Archived ones are left out unless asked
+/// for and soft-deleted ones always, except under `changed_since`, which returns both so a
+/// syncing client hears about every change.
+fn push_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    user: CurrentUser,
+    filter: &TodoFilter,
+) -> Result<(), ApiError> {
+    builder.push(" WHERE user_id IS NOT DISTINCT FROM ").push_bind(user.0);
+    match filter.changed_since()? {
+        Some(since) => {
+            builder.push(" AND updated_at > ").push_bind(since);
+        }
+        None => {
+            builder.push(" AND deleted_at IS NULL");
+            if !filter.include_archived {
+                builder.push(" AND NOT archived");
+            }
+        }
+    }
+
+    if let Some(ids) = filter.ids()? {
+        builder.push(" AND id = ANY(").push_bind(ids).push(")");
+    }
+
+    if let Some(completed) = filter.completed {
+        builder.push(" AND completed = ").push_bind(completed);
+    }
+
+    if let Some(search) = filter.search_term() {
+        let pattern = escape_like(search);
+        builder
+            .push(" AND (title ILIKE '%' || ")
+            .push_bind(pattern.clone())
+            .push(" || '%' OR description ILIKE '%' || ")
+            .push_bind(pattern)
+            .push(" || '%')");
+    }
+
+    if let Some(color) = filter.color()? {
+        builder.push(" AND color = ").push_bind(color);
+    }
+
+    if let Some(tag) = filter.tag() {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM todo_tags WHERE todo_id = todos.id AND tag = ")
+            .push_bind(tag)
+            .push(")");
+    }
+
+    let created = filter.created_range()?;
+    if let Some(after) = created.after {
+        builder.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = created.before {
+        builder.push(" AND created_at <= ").push_bind(before);
+    }
+    Ok(())
+}
+
+/// Only the list returns the tombstones `changed_since` brings in, marked by `deleted_at`;
+/// anywhere else they would be counted or changed as if they were live
+fn reject_changed_since(filter: &TodoFilter) -> Result<(), ApiError> {
+    if filter.changed_since.is_some() {
+        return Err(ApiError::BadRequest(
+            "changed_since can only be used with GET /api/todos".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Escape LIKE wildcards so user input is matched literally
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Whether the client ranks `text/plain` above JSON in its `Accept` header.
+/// Wildcards and a missing header select JSON, so existing clients are unaffected.
+fn prefers_plain_text(http_req: &HttpRequest) -> bool {
+    let Some(accept) = http_req.get_header::<Accept>() else {
+        return false;
+    };
+    accept
+        .ranked()
+        .iter()
+        .find(|mime| matches!(mime.essence_str(), "text/plain" | "application/json"))
+        .is_some_and(|mime| mime.essence_str() == "text/plain")
+}
+
+/// The `text/plain` rendering of a todo, e.g. `[x] Buy milk`
+fn plain_text_line(todo: &Todo) -> String {
+    let mark = if todo.completed { 'x' } else { ' ' };
+    format!("[{}] {}\n", mark, todo.title)
+}
+
+/// SQLSTATE for text the server cannot convert to the connection's UTF-8, which happens with
+/// bytes that aren't valid UTF-8 stored in a `SQL_ASCII` database
+const CHARACTER_NOT_IN_REPERTOIRE: &str = "22021";
+
+fn is_unreadable_text(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db_err)
+        if db_err.code().as_deref() == Some(CHARACTER_NOT_IN_REPERTOIRE))
+}
+
+/// Load the listed todos one by one after the list query hit unreadable text, leaving out
+/// (and naming) the rows that hold it
+async fn fetch_readable(pool: &PgPool, ids: &[Uuid]) -> Result<Vec<PgRow>, ApiError> {
+    let query = format!("SELECT {} FROM todos WHERE id = $1", TODO_COLUMNS);
+    let mut rows = Vec::with_capacity(ids.len());
+    for &id in ids {
+        match sqlx::query(&query).bind(id).fetch_one(pool).await {
+            Ok(row) => rows.push(row),
+            Err(err) if is_unreadable_text(&err) => {
+                log::warn!(
+                    "Skipping todo {} in the list, its text is not valid UTF-8: {}",
+                    id,
+                    err
+                );
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(rows)
+}
+
+/// Decode listed rows one at a time, so a row holding data `Todo` can't represent (such as
+/// text that isn't valid UTF-8 in a `SQL_ASCII` database) is left out of the page with a
+/// warning naming it, instead of failing the whole list
+fn decode_listed(rows: Vec<PgRow>) -> Vec<Todo> {
+    rows.iter()
+        .filter_map(|row| match Todo::from_row(row) {
+            Ok(todo) => Some(todo),
+            Err(err) => {
+                let id = row
+                    .try_get::<Uuid, _>("id")
+                    .map_or_else(|_| "?".to_string(), |id| id.to_string());
+                log::warn!("Skipping todo {} in the list, it failed to decode: {}", id, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A todo as JSON with only the selected keys and its timestamps in `tz`. Only called when
+/// `fields` or `tz` was given, since building a map is slower than serializing the struct.
+fn render(todo: TodoResponse, fields: Option<&[&str]>, tz: Option<Tz>) -> serde_json::Value {
+    let timestamps = [("created_at", todo.created_at), ("updated_at", todo.updated_at)];
+    let mut json = serde_json::to_value(todo).expect("TodoResponse serializes to JSON");
+    if let serde_json::Value::Object(map) = &mut json {
+        if let Some(tz) = tz {
+            for (key, at) in timestamps {
+                let local = at.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::AutoSi, true);
+                map.insert(key.to_string(), local.into());
+            }
+        }
+        if let Some(fields) = fields {
+            map.retain(|key, _| fields.contains(&key.as_str()));
+        }
+    }
+    json
+}
+
+/// List todos, one page at a time
+#[utoipa::path(
+    get,
+    path = "/api/todos",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(PaginationParams, TodoFilter, SortParams, OutputParams),
+    responses(
+        (status = 200, description = "One page of todos", content(
+            ("application/json" = [TodoResponse]),
+            ("text/plain" = String, example = json!("[x] Buy milk\n[ ] Walk the dog\n")),
+        ), headers(
+            ("X-Total-Count" = i64, description = "Todos matching the filters across all pages"),
+            ("Link" = String, description = "first/prev/next/last page URLs"),
+            ("X-Next-Cursor" = String,
+             description = "`cursor` for the next page, when sorted by created_at and one follows"),
+            ("X-Sync-Watermark" = String,
+             description = "Server time shortly before the list was read, to pass as the next \
+                            `changed_since`"),
+        )),
+        (status = 400, description = "Invalid pagination, cursor, sort, fields or tz parameters",
+            body = ErrorResponse),
+    )
+)]
+pub async fn list_todos(
+    pool: web::Data<PgPool>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    pagination: web::Query<PaginationParams>,
+    filter: web::Query<TodoFilter>,
+    sort: web::Query<SortParams>,
+    output: web::Query<OutputParams>,
+) -> Result<HttpResponse, ApiError> {
+    let (mut limit, offset) = pagination.resolve()?;
+    // Every requested todo fits on one page unless the client asked for smaller ones
+    if let (Some(ids), None) = (filter.ids()?, pagination.limit) {
+        limit = limit.max(ids.len() as i64);
+    }
+    let cursor = pagination.cursor()?;
+    let (sort_field, sort_order) = sort.resolve()?;
+    if cursor.is_some() && (sort_field != SortField::CreatedAt || sort.pending_first) {
+        return Err(ApiError::BadRequest(
+            "cursor can only be used with sort_by=created_at, without pending_first".to_string(),
+        ));
+    }
+    let fields = output.fields()?;
+    let tz = output.tz()?;
+    // Set back from the time of the read, so a write that stamped `updated_at` before it but
+    // had not committed yet is returned by the next delta sync rather than missed
+    let watermark = (Utc::now() - Duration::seconds(SYNC_WATERMARK_OVERLAP_SECS))
+        .to_rfc3339_opts(SecondsFormat::Micros, true);
+
+    let mut count_query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM todos");
+    push_filters(&mut count_query, user, &filter)?;
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    let list_query = |columns: &str| -> Result<QueryBuilder<'static, Postgres>, ApiError> {
+        let mut query = QueryBuilder::new(format!("SELECT {} FROM todos", columns));
+        push_filters(&mut query, user, &filter)?;
+        if let Some(cursor) = &cursor {
+            let beyond = if sort_order == SortOrder::Asc { ">" } else { "<" };
+            query
+                .push(format!(" AND (created_at, id) {} (", beyond))
+                .push_bind(cursor.created_at)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        // `id` breaks ties so rows sharing a sort value keep one order across pages;
+        // without a total order LIMIT/OFFSET can skip or repeat them. One row past the page
+        // tells whether another page follows.
+        query
+            .push(" ORDER BY ")
+            .push(if sort.pending_first { "completed, " } else { "" })
+            .push(format!(
+                "{} {order}, id {order}",
+                sort_field.column(),
+                order = sort_order.keyword()
+            ))
+            .push(" LIMIT ")
+            .push_bind(limit + 1)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        Ok(query)
+    };
+    let (rows, listed) = match list_query(TODO_COLUMNS)?.build().fetch_all(pool.get_ref()).await {
+        Ok(rows) => {
+            let listed = rows.len();
+            (rows, listed)
+        }
+        Err(err) if is_unreadable_text(&err) => {
+            let ids: Vec<Uuid> =
+                list_query("id")?.build_query_scalar().fetch_all(pool.get_ref()).await?;
+            (fetch_readable(&pool, &ids).await?, ids.len())
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let has_next = listed as i64 > limit;
+    let mut todos = decode_listed(rows);
+    todos.truncate(limit as usize);
+
+    // Any page in plain created_at order can hand over to cursor paging, not only cursor pages
+    let next_cursor = todos
+        .last()
+        .filter(|_| has_next && sort_field == SortField::CreatedAt && !sort.pending_first)
+        .map(|todo| Cursor { created_at: todo.created_at, id: todo.id });
+    let link = if cursor.is_some() {
+        cursor_link_header(http_req.path(), http_req.query_string(), limit, next_cursor.as_ref())
+    } else {
+        link_header(http_req.path(), http_req.query_string(), limit, offset, total)
+    };
+    let mut builder = HttpResponse::Ok();
+    builder
+        .insert_header((LINK, link))
+        .insert_header(("X-Total-Count", total.to_string()))
+        .insert_header(("X-Sync-Watermark", watermark))
+        .insert_header((VARY, "Accept"));
+    if let Some(next) = next_cursor {
+        builder.insert_header(("X-Next-Cursor", next.encode()));
+    }
+
+    if prefers_plain_text(&http_req) {
+        let text: String = todos.iter().map(plain_text_line).collect();
+        return Ok(builder.content_type(ContentType::plaintext()).body(text));
+    }
+    let response: Vec<TodoResponse> = todos.into_iter().map(|t| t.into()).collect();
+    if fields.is_none() && tz.is_none() {
+        return Ok(builder.json(response));
+    }
+    let rendered: Vec<_> =
+        response.into_iter().map(|todo| render(todo, fields.as_deref(), tz)).collect();
+    Ok(builder.json(rendered))
+}
+
+/// Count todos by state in a single query
+#[utoipa::path(
+    get,
+    path = "/api/todos/stats",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "Todo counts", body = TodoStats),
+    )
+)]
+pub async fn todo_stats(
+    pool: web::Data<PgPool>,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    let stats = sqlx::query_as::<_, TodoStats>(
+        "SELECT COUNT(*) AS total,
+                COUNT(*) FILTER (WHERE completed) AS completed,
+                COUNT(*) FILTER (WHERE NOT completed) AS pending,
+                COUNT(*) FILTER (WHERE NOT completed AND due_date < now()) AS overdue,
+                COALESCE(SUM(estimated_minutes) FILTER (WHERE completed), 0)
+                    AS completed_estimated_minutes,
+                COALESCE(SUM(actual_minutes) FILTER (WHERE completed), 0)
+                    AS completed_actual_minutes
+         FROM todos
+         WHERE deleted_at IS NULL AND user_id IS NOT DISTINCT FROM $1"
     )
+    .bind(user.0)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// List pending and completed todos side by side, for a two-column board
+#[utoipa::path(
+    get,
+    path = "/api/todos/board",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(PaginationParams, TodoFilter, SortParams),
+    responses(
+        (status = 200, description = "One page of each group", body = TodoBoard, headers(
+            ("X-Pending-Count" = i64, description = "Pending todos matching the filters"),
+            ("X-Completed-Count" = i64, description = "Completed todos matching the filters"),
+        )),
+        (status = 400, description = "Invalid pagination, filter or sort parameters",
+            body = ErrorResponse),
+    )
+)]
+pub async fn todo_board(
+    pool: web::Data<PgPool>,
+    user: CurrentUser,
+    pagination: web::Query<PaginationParams>,
+    filter: web::Query<TodoFilter>,
+    sort: web::Query<SortParams>,
+) -> Result<HttpResponse, ApiError> {
+    let (limit, offset) = pagination.resolve()?;
+    if pagination.cursor.is_some() {
+        return Err(ApiError::BadRequest(
+            "cursor cannot be used with the board; page it with limit and offset".to_string(),
+        ));
+    }
+    if filter.completed.is_some() {
+        return Err(ApiError::BadRequest(
+            "completed cannot be used with the board, which groups by it".to_string(),
+        ));
+    }
+    reject_changed_since(&filter)?;
+    let (sort_field, sort_order) = sort.resolve()?;
+    let order_by = format!(
+        " ORDER BY {} {order}, id {order}",
+        sort_field.column(),
+        order = sort_order.keyword()
+    );
+
+    let mut count_query = QueryBuilder::<Postgres>::new(
+        "SELECT COUNT(*) FILTER (WHERE NOT completed), COUNT(*) FILTER (WHERE completed)
+         FROM todos",
+    );
+    push_filters(&mut count_query, user, &filter)?;
+    let (pending_total, completed_total): (i64, i64) = count_query
+        .build_query_as()
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    // One page of each group in a single statement, so both come from the same snapshot.
+    // `limit` and `offset` apply to each group on its own.
+    let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM (");
+    for completed in [false, true] {
+        if completed {
+            query.push(" UNION ALL ");
+        }
+        query.push(format!("(SELECT {} FROM todos", TODO_COLUMNS));
+        push_filters(&mut query, user, &filter)?;
+        query
+            .push(" AND completed = ")
+            .push_bind(completed)
+            .push(&order_by)
+            .push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset)
+            .push(")");
+    }
+    query.push(") AS board").push(&order_by);
+    let todos = query.build_query_as::<Todo>().fetch_all(pool.get_ref()).await?;
+
+    let (completed, pending) =
+        todos.into_iter().map(TodoResponse::from).partition(|todo| todo.completed);
+    Ok(HttpResponse::Ok()
+        .insert_header(("X-Pending-Count", pending_total.to_string()))
+        .insert_header(("X-Completed-Count", completed_total.to_string()))
+        .json(TodoBoard { pending, completed }))
+}
+
+/// IDs and `updated_at` of every todo the list would return, for clients that sync
+#[utoipa::path(
+    get,
+    path = "/api/todos/ids",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(TodoFilter),
+    responses(
+        (status = 200, description = "Every matching todo, oldest first", body = [TodoStamp]),
+        (status = 400, description = "Invalid filter parameters", body = ErrorResponse),
+    )
+)]
+pub async fn list_todo_ids(
+    pool: web::Data<PgPool>,
+    user: CurrentUser,
+    filter: web::Query<TodoFilter>,
+) -> Result<HttpResponse, ApiError> {
+    reject_changed_since(&filter)?;
+    // Not paginated: two columns per todo stay small even for long lists
+    let mut query = QueryBuilder::<Postgres>::new("SELECT id, updated_at FROM todos");
+    push_filters(&mut query, user, &filter)?;
+    query.push(" ORDER BY created_at, id");
+    let stamps = query
+        .build_query_as::<TodoStamp>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(stamps))
+}
+
+/// Soft-deleted todos, most recently deleted first, one page at a time
+#[utoipa::path(
+    get,
+    path = "/api/todos/trash",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "One page of deleted todos", body = [TodoResponse], headers(
+            ("X-Total-Count" = i64, description = "Deleted todos across all pages"),
+            ("Link" = String, description = "first/prev/next/last page URLs"),
+        )),
+        (status = 400, description = "Invalid pagination parameters", body = ErrorResponse),
+    )
+)]
+pub async fn list_trash(
+    pool: web::Data<PgPool>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    pagination: web::Query<PaginationParams>,
+) -> Result<HttpResponse, ApiError> {
+    let (limit, offset) = pagination.resolve()?;
+    if pagination.cursor.is_some() {
+        return Err(ApiError::BadRequest(
+            "cursor cannot be used with the trash; page it with limit and offset".to_string(),
+        ));
+    }
+
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM todos
+         WHERE user_id IS NOT DISTINCT FROM $1 AND deleted_at IS NOT NULL"
+    )
+    .bind(user.0)
+    .fetch_one(pool.get_ref())
+    .await?;
+    let todos = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE user_id IS NOT DISTINCT FROM $1 AND deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC, id DESC
+         LIMIT $2 OFFSET $3",
+        TODO_COLUMNS
+    ))
+    .bind(user.0)
+    .bind(limit)
+    .bind(offset)
     .fetch_all(pool.get_ref())
     .await?;
 
+    let link = link_header(http_req.path(), http_req.query_string(), limit, offset, total);
     let response: Vec<TodoResponse> = todos.into_iter().map(|t| t.into()).collect();
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok()
+        .insert_header((LINK, link))
+        .insert_header(("X-Total-Count", total.to_string()))
+        .json(response))
 }
 
-/// Get a single todo by ID
+/// Live todos whose title starts with `q`, for completing a title as it is typed
+#[utoipa::path(
+    get,
+    path = "/api/todos/autocomplete",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(AutocompleteParams),
+    responses(
+        (status = 200, description = "Matching todos, by title", body = [TodoSuggestion]),
+        (status = 400, description = "Blank `q` or invalid `limit`", body = ErrorResponse),
+    )
+)]
+pub async fn autocomplete_todos(
+    pool: web::Data<PgPool>,
+    user: CurrentUser,
+    params: web::Query<AutocompleteParams>,
+) -> Result<HttpResponse, ApiError> {
+    let (prefix, limit) = params.resolve()?;
+    // Written to match idx_title_prefix, so the prefix is looked up rather than scanned for
+    let suggestions = sqlx::query_as::<_, TodoSuggestion>(
+        "SELECT id, title FROM todos
+         WHERE lower(title) LIKE lower($1) || '%'
+           AND deleted_at IS NULL AND NOT archived
+           AND user_id IS NOT DISTINCT FROM $2
+         ORDER BY lower(title), id
+         LIMIT $3"
+    )
+    .bind(escape_like(prefix))
+    .bind(user.0)
+    .bind(limit)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+/// Entity tag for a todo; every write bumps `updated_at`, so it changes with the content
+fn etag(todo: &Todo) -> EntityTag {
+    EntityTag::new_strong(todo.updated_at.timestamp_micros().to_string())
+}
+
+/// Reject the request with 412 when an `If-Match` header doesn't match the current version
+fn check_if_match(http_req: &HttpRequest, todo: &Todo) -> Result<(), ApiError> {
+    match http_req.get_header::<IfMatch>() {
+        Some(IfMatch::Items(tags)) if !tags.iter().any(|tag| tag.strong_eq(&etag(todo))) => {
+            Err(ApiError::PreconditionFailed(format!(
+                "Todo with id {} has been modified since it was fetched",
+                todo.id
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Get a single todo by ID, answering 304 when `If-None-Match` still matches
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID"), OutputParams),
+    responses(
+        (status = 200, description = "The todo", content(
+            ("application/json" = TodoResponse),
+            ("text/plain" = String, example = json!("[x] Buy milk\n")),
+        ), headers(
+            ("ETag" = String, description = "Current version of the todo"),
+        )),
+        (status = 400, description = "Unknown `fields` key or `tz`", body = ErrorResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
 pub async fn get_todo(
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
+    output: web::Query<OutputParams>,
+    http_req: HttpRequest,
+    user: CurrentUser,
 ) -> Result<HttpResponse, ApiError> {
-    let todo = sqlx::query_as::<_, Todo>(
-        "SELECT id, title, description, completed, created_at, updated_at FROM todos WHERE id = $1"
+    let id = id.into_inner();
+    let fields = output.fields()?;
+    let tz = output.tz()?;
+
+    // Another user's todo is reported as missing so IDs don't leak
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+
+    let current = etag(&todo);
+    let not_modified = match http_req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&current)),
+        None => false,
+    };
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((ETAG, current.to_string()))
+            .finish());
+    }
+
+    let mut builder = HttpResponse::Ok();
+    builder
+        .insert_header((ETAG, current.to_string()))
+        .insert_header((VARY, "Accept"));
+
+    if prefers_plain_text(&http_req) {
+        return Ok(builder
+            .content_type(ContentType::plaintext())
+            .body(plain_text_line(&todo)));
+    }
+    let response = TodoResponse::from(todo);
+    if fields.is_none() && tz.is_none() {
+        return Ok(builder.json(response));
+    }
+    Ok(builder.json(render(response, fields.as_deref(), tz)))
+}
+
+/// Replace the tags of a todo, returning the stored set in display order
+async fn replace_tags(
+    conn: &mut PgConnection,
+    todo_id: Uuid,
+    tags: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
+        .bind(todo_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO todo_tags (todo_id, tag)
+         SELECT $1, UNNEST($2::text[])
+         ON CONFLICT DO NOTHING"
     )
-    .bind(id.into_inner())
-    .fetch_one(pool.get_ref())
+    .bind(todo_id)
+    .bind(tags)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query_scalar("SELECT ARRAY(SELECT tag FROM todo_tags WHERE todo_id = $1 ORDER BY tag)")
+        .bind(todo_id)
+        .fetch_one(&mut *conn)
+        .await
+}
+
+/// Check that a new todo's parent is a live todo owned by `user`
+async fn ensure_parent_exists(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    parent_id: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM todos
+             WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL
+         )"
+    )
+    .bind(parent_id)
+    .bind(user.0)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!("Parent todo with id {} not found", parent_id)))
+    }
+}
+
+/// Gap left between neighbouring positions when todos are appended or renumbered
+const POSITION_STEP: f64 = 1024.0;
+/// Two neighbours closer than this are too close to split again; the user's todos are
+/// renumbered first. Halving 1024 gets here after about 30 moves into the same gap.
+const MIN_POSITION_GAP: f64 = 1e-6;
+
+/// Insert a todo owned by `user` and its tags; callers wrap this in a transaction
+async fn insert_todo(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    new_todo: &NewTodo,
+) -> Result<Todo, sqlx::Error> {
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+
+    // New todos go to the end of the user's manual order
+    let mut todo = sqlx::query_as::<_, Todo>(&format!(
+        "INSERT INTO todos
+             (id, title, description, completed, due_date, priority, created_at, updated_at,
+              user_id, parent_id, recurrence, metadata, color, estimated_minutes,
+              actual_minutes, position)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+             (SELECT COALESCE(MAX(position), 0) + $16 FROM todos
+              WHERE user_id IS NOT DISTINCT FROM $9))
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(&new_todo.title)
+    .bind(&new_todo.description)
+    .bind(false)
+    .bind(new_todo.due_date)
+    .bind(new_todo.priority)
+    .bind(now)
+    .bind(now)
+    .bind(user.0)
+    .bind(new_todo.parent_id)
+    .bind(new_todo.recurrence)
+    .bind(&new_todo.metadata)
+    .bind(&new_todo.color)
+    .bind(new_todo.estimated_minutes)
+    .bind(new_todo.actual_minutes)
+    .bind(POSITION_STEP)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if !new_todo.tags.is_empty() {
+        todo.tags = replace_tags(conn, id, &new_todo.tags).await?;
+    }
+
+    Ok(todo)
+}
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set on a create response that was replayed for a repeated `Idempotency-Key`
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Matches the `VARCHAR(255)` key column
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+pub(crate) const DEFAULT_IDEMPOTENCY_KEY_TTL_SECS: u32 = 24 * 60 * 60;
+
+/// How long `create_todo` remembers an `Idempotency-Key`
+#[derive(Debug, Clone, Copy)]
+pub struct IdempotencyKeyTtl(Duration);
+
+impl IdempotencyKeyTtl {
+    pub fn new(ttl: Duration) -> Self {
+        Self(ttl)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Default for IdempotencyKeyTtl {
+    fn default() -> Self {
+        Self::new(Duration::seconds(i64::from(DEFAULT_IDEMPOTENCY_KEY_TTL_SECS)))
+    }
+}
+
+/// Most live todos a user may have, from `MAX_TODOS`. Without JWT auth every todo shares
+/// the one anonymous owner, so it caps the whole instance.
+#[derive(Debug, Clone, Copy)]
+pub struct TodoLimit(i64);
+
+impl TodoLimit {
+    pub fn new(max_todos: u32) -> Self {
+        Self(i64::from(max_todos))
+    }
+
+    pub fn max_todos(&self) -> i64 {
+        self.0
+    }
+}
+
+/// Completed todos may only be reopened, not edited, by `PUT` and `PATCH`; registered when
+/// `LOCK_COMPLETED` is set
+#[derive(Debug, Clone, Copy)]
+pub struct CompletedLock;
+
+impl CompletedLock {
+    /// `Some` only when `LOCK_COMPLETED` is set to true
+    pub fn from_env() -> Option<Self> {
+        env::var("LOCK_COMPLETED")
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .then_some(Self)
+    }
+}
+
+/// Reject the request with 409 under the `CompletedLock` if it changes a completed todo in any
+/// way other than reopening it
+fn check_completed_lock(
+    http_req: &HttpRequest,
+    existing: &Todo,
+    update: &TodoUpdate,
+) -> Result<(), ApiError> {
+    let locked = http_req.app_data::<web::Data<CompletedLock>>().is_some();
+    if locked && existing.completed && update.changes_details(existing) {
+        return Err(ApiError::Conflict(format!(
+            "Todo with id {} is completed; reopen it before editing it",
+            existing.id
+        )));
+    }
+    Ok(())
+}
+
+/// Reject the request with 409 if the todos it inserted took `user` over the `TodoLimit`.
+///
+/// Called after the inserts so bulk creates and imports count exactly what they added.
+/// Takes a transaction-scoped advisory lock on the user first, so concurrent creates check
+/// one after another and each sees the others' committed todos.
+async fn enforce_todo_limit(
+    conn: &mut PgConnection,
+    http_req: &HttpRequest,
+    user: CurrentUser,
+) -> Result<(), ApiError> {
+    let Some(limit) = http_req.app_data::<web::Data<TodoLimit>>().map(|l| *l.get_ref()) else {
+        return Ok(());
+    };
+    sqlx::query(
+        "SELECT pg_advisory_xact_lock(
+             hashtextextended('todo_limit:' || COALESCE($1::text, ''), 0)
+         )"
+    )
+    .bind(user.0)
+    .execute(&mut *conn)
+    .await?;
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM todos WHERE user_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL"
+    )
+    .bind(user.0)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if count > limit.max_todos() {
+        // Dropping the caller's transaction rolls the inserts back
+        Err(ApiError::Conflict(format!(
+            "Todo limit of {} reached; delete some todos before adding more",
+            limit.max_todos()
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// The request's `Idempotency-Key`, if it sent one
+fn idempotency_key(http_req: &HttpRequest) -> Result<Option<String>, ApiError> {
+    let Some(value) = http_req.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_IDEMPOTENCY_KEY_LENGTH
+            ))
+        })?;
+    Ok(Some(key.to_string()))
+}
+
+/// The todo an unexpired `key` created, in its current state
+async fn find_idempotent_todo(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    key: &str,
+    cutoff: DateTime<Utc>,
+) -> Result<Option<Todo>, sqlx::Error> {
+    sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE id = (
+             SELECT todo_id FROM idempotency_keys
+             WHERE user_id IS NOT DISTINCT FROM $1 AND key = $2 AND created_at > $3
+         )",
+        TODO_COLUMNS
+    ))
+    .bind(user.0)
+    .bind(key)
+    .bind(cutoff)
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+/// Reject `title` if one of the user's live, incomplete todos already has it, ignoring case.
+///
+/// Takes a transaction-scoped advisory lock on the user and title first, so two concurrent
+/// creates of the same title queue up here and the second sees the first's todo.
+async fn ensure_not_duplicate(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    title: &str,
+) -> Result<(), ApiError> {
+    // Without JWT auth the user is NULL, which would make the key (and the lock) NULL too
+    sqlx::query(
+        "SELECT pg_advisory_xact_lock(
+             hashtextextended(COALESCE($1::text, '') || ':' || lower($2), 0)
+         )"
+    )
+    .bind(user.0)
+    .bind(title)
+    .execute(&mut *conn)
+    .await?;
+
+    let existing: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM todos
+         WHERE user_id IS NOT DISTINCT FROM $1 AND lower(title) = lower($2)
+           AND NOT completed AND deleted_at IS NULL
+         LIMIT 1"
+    )
+    .bind(user.0)
+    .bind(title)
+    .fetch_optional(&mut *conn)
     .await?;
 
-    Ok(HttpResponse::Ok().json(TodoResponse::from(todo)))
+    match existing {
+        Some(id) => Err(ApiError::Conflict(format!(
+            "Todo with id {} already has the title '{}'",
+            id, title
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `201 Created` with a `Location` pointing at the new todo, under the `ApiBasePath`
+fn created(http_req: &HttpRequest, todo: &TodoResponse) -> HttpResponseBuilder {
+    let path = format!("/api/todos/{}", todo.id);
+    let location = match http_req.app_data::<web::Data<ApiBasePath>>() {
+        Some(base) => base.join(&path),
+        None => path,
+    };
+    let mut builder = HttpResponse::Created();
+    builder.insert_header((LOCATION, location));
+    builder
 }
 
-/// Create a new todo
+fn replayed(http_req: &HttpRequest, todo: Todo) -> HttpResponse {
+    let response = TodoResponse::from(todo);
+    created(http_req, &response)
+        .insert_header((IDEMPOTENT_REPLAYED, "true"))
+        .json(response)
+}
+
+/// Create a new todo. A retry carrying the same `Idempotency-Key` as an earlier create
+/// gets that todo back instead of a duplicate.
+#[utoipa::path(
+    post,
+    path = "/api/todos",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "Client-chosen key that makes retries of this create safe"),
+        CreateParams,
+    ),
+    request_body = CreateTodoRequest,
+    responses(
+        (status = 201, description = "Todo created, or replayed for a known key",
+            body = TodoResponse,
+            headers(
+                ("Location" = String, description = "Path of the created todo"),
+                ("Idempotent-Replayed" = bool, description = "Set when replayed"),
+            )),
+        (status = 400, description = "Invalid request body", body = ErrorResponse),
+        (status = 409, description = "With dedupe, an incomplete todo has the same title",
+            body = ErrorResponse),
+    )
+)]
 pub async fn create_todo(
     pool: web::Data<PgPool>,
     req: web::Json<CreateTodoRequest>,
+    params: web::Query<CreateParams>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
 ) -> Result<HttpResponse, ApiError> {
-    if req.title.trim().is_empty() {
-        return Err(ApiError::BadRequest("Title cannot be empty".to_string()));
+    let new_todo = req.validate()?;
+    let key = idempotency_key(&http_req)?;
+    let ttl = http_req
+        .app_data::<web::Data<IdempotencyKeyTtl>>()
+        .map_or_else(IdempotencyKeyTtl::default, |ttl| *ttl.get_ref());
+    let now = Utc::now();
+    let cutoff = now - ttl.duration();
+
+    let mut tx = pool.begin().await?;
+    if let Some(key) = &key {
+        if let Some(todo) = find_idempotent_todo(&mut tx, user, key, cutoff).await? {
+            return Ok(replayed(&http_req, todo));
+        }
+        // Expired keys are swept here too, since an expired key that the cleanup has not
+        // removed yet would otherwise block the insert below
+        sqlx::query("DELETE FROM idempotency_keys WHERE created_at <= $1")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
     }
 
-    let id = Uuid::new_v4();
+    if params.dedupe {
+        ensure_not_duplicate(&mut tx, user, &new_todo.title).await?;
+    }
+    ensure_parent_exists(&mut tx, user, new_todo.parent_id).await?;
+    let todo = insert_todo(&mut tx, user, &new_todo).await?;
+    enforce_todo_limit(&mut tx, &http_req, user).await?;
+
+    if let Some(key) = &key {
+        // Waits for a concurrent create with the same key, and inserts nothing if it won
+        let stored = sqlx::query(
+            "INSERT INTO idempotency_keys (user_id, key, todo_id, created_at)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (user_id, key) DO NOTHING"
+        )
+        .bind(user.0)
+        .bind(key)
+        .bind(todo.id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            == 1;
+
+        if !stored {
+            tx.rollback().await?;
+            let mut conn = pool.acquire().await?;
+            return find_idempotent_todo(&mut conn, user, key, cutoff)
+                .await?
+                .map(|todo| replayed(&http_req, todo))
+                .ok_or_else(|| {
+                    ApiError::Conflict("Idempotency-Key is in use by another request".to_string())
+                });
+        }
+    }
+    tx.commit().await?;
+
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Created { todo: response.clone() });
+    Ok(created(&http_req, &response).json(response))
+}
+
+/// Validate every item of a bulk request up front so the response lists all problems,
+/// with each field prefixed by its index (e.g. `[2].title`)
+fn validate_all<T, U>(
+    items: &[T],
+    validate: impl Fn(&T) -> Result<U, ApiError>,
+) -> Result<Vec<U>, ApiError> {
+    let mut valid = Vec::with_capacity(items.len());
+    let mut fields = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match validate(item) {
+            Ok(value) => valid.push(value),
+            Err(ApiError::Validation(item_fields)) => {
+                fields.extend(item_fields.into_iter().map(|field| FieldError {
+                    field: format!("[{}].{}", index, field.field),
+                    message: field.message,
+                }));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    if !fields.is_empty() {
+        return Err(ApiError::Validation(fields));
+    }
+    Ok(valid)
+}
+
+/// Create several todos in a single transaction
+#[utoipa::path(
+    post,
+    path = "/api/todos/batch",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    request_body = Vec<CreateTodoRequest>,
+    responses(
+        (status = 201, description = "All todos created", body = [TodoResponse]),
+        (status = 400, description = "Empty batch or an invalid item", body = ErrorResponse),
+    )
+)]
+pub async fn batch_create_todos(
+    pool: web::Data<PgPool>,
+    req: web::Json<Vec<CreateTodoRequest>>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    if req.is_empty() {
+        return Err(ApiError::BadRequest("Batch cannot be empty".to_string()));
+    }
+
+    let new_todos = validate_all(&req, CreateTodoRequest::validate)?;
+
+    let mut tx = pool.begin().await?;
+    let mut created = Vec::with_capacity(new_todos.len());
+    for new_todo in &new_todos {
+        ensure_parent_exists(&mut tx, user, new_todo.parent_id).await?;
+        created.push(TodoResponse::from(insert_todo(&mut tx, user, new_todo).await?));
+    }
+    enforce_todo_limit(&mut tx, &http_req, user).await?;
+    tx.commit().await?;
+
+    for todo in &created {
+        bus.publish(user, TodoEvent::Created { todo: todo.clone() });
+    }
+
+    Ok(HttpResponse::Created().json(created))
+}
+
+/// Insert an imported row owned by `user`, returning `None` when its ID is already taken.
+/// Callers wrap this in a transaction.
+async fn insert_imported(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    imported: &ImportedTodo,
+) -> Result<Option<Todo>, sqlx::Error> {
+    let id = imported.id.unwrap_or_else(Uuid::new_v4);
     let now = Utc::now();
+    let created_at = imported.created_at.unwrap_or(now);
+    let updated_at = imported.updated_at.unwrap_or(created_at);
+    let completed_at = if imported.completed {
+        Some(imported.completed_at.unwrap_or(updated_at))
+    } else {
+        None
+    };
+
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "INSERT INTO todos
+             (id, title, description, completed, completed_at, due_date, priority,
+              created_at, updated_at, user_id, metadata, color, estimated_minutes,
+              actual_minutes, position)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14,
+             (SELECT COALESCE(MAX(position), 0) + $15 FROM todos
+              WHERE user_id IS NOT DISTINCT FROM $10))
+         ON CONFLICT (id) DO NOTHING
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(&imported.title)
+    .bind(&imported.description)
+    .bind(imported.completed)
+    .bind(completed_at)
+    .bind(imported.due_date)
+    .bind(imported.priority)
+    .bind(created_at)
+    .bind(updated_at)
+    .bind(user.0)
+    .bind(&imported.metadata)
+    .bind(&imported.color)
+    .bind(imported.estimated_minutes)
+    .bind(imported.actual_minutes)
+    .bind(POSITION_STEP)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(mut todo) = todo else {
+        return Ok(None);
+    };
+    if !imported.tags.is_empty() {
+        todo.tags = replace_tags(conn, id, &imported.tags).await?;
+    }
 
-    let todo = sqlx::query_as::<_, Todo>(
-        "INSERT INTO todos (id, title, description, completed, created_at, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6)
-         RETURNING id, title, description, completed, created_at, updated_at"
+    Ok(Some(todo))
+}
+
+/// Import todos in one transaction, as new todos or with their original IDs
+#[utoipa::path(
+    post,
+    path = "/api/todos/import",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(ImportParams),
+    request_body = Vec<ImportTodoRequest>,
+    responses(
+        (status = 201, description = "Todos imported", body = ImportResponse),
+        (status = 400, description = "Invalid item or parameter", body = ErrorResponse),
+        (status = 409, description = "An ID is taken and `on_conflict=fail`", body = ErrorResponse),
     )
+)]
+pub async fn import_todos(
+    pool: web::Data<PgPool>,
+    req: web::Json<Vec<ImportTodoRequest>>,
+    params: web::Query<ImportParams>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let on_conflict = params.on_conflict()?;
+    let mut rows = validate_all(&req, ImportTodoRequest::validate)?;
+    if !params.preserve_ids {
+        for row in &mut rows {
+            row.id = None;
+            row.created_at = None;
+            row.updated_at = None;
+            row.completed_at = None;
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut imported = Vec::with_capacity(rows.len());
+    let mut skipped = Vec::new();
+    for row in &rows {
+        match insert_imported(&mut tx, user, row).await? {
+            Some(todo) => imported.push(TodoResponse::from(todo)),
+            // Only preserved IDs can collide; freshly generated ones never do
+            None => {
+                let id = row.id.unwrap_or_default();
+                if on_conflict == OnConflict::Fail {
+                    // Dropping `tx` rolls back everything inserted so far
+                    return Err(ApiError::Conflict(format!("Todo with id {} already exists", id)));
+                }
+                skipped.push(id);
+            }
+        }
+    }
+    enforce_todo_limit(&mut tx, &http_req, user).await?;
+    tx.commit().await?;
+
+    for todo in &imported {
+        bus.publish(user, TodoEvent::Created { todo: todo.clone() });
+    }
+
+    Ok(HttpResponse::Created().json(ImportResponse {
+        imported: imported.len(),
+        skipped,
+    }))
+}
+
+/// Write a full set of values to an existing todo, returning `None` if it does not
+/// exist or its version no longer matches `update.expected_version`.
+/// Callers wrap this in a transaction.
+async fn write_todo(
+    conn: &mut PgConnection,
+    id: Uuid,
+    update: &TodoUpdate,
+) -> Result<Option<Todo>, sqlx::Error> {
+    // `completed` on the right-hand side is the value before this update, so
+    // completed_at is only stamped on the false -> true transition
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET title = $1, description = $2, completed = $3, due_date = $4,
+             priority = $5, updated_at = $6, recurrence = $9, metadata = $10,
+             color = $11, estimated_minutes = $12, actual_minutes = $13,
+             version = version + 1,
+             completed_at = CASE
+                 WHEN NOT $3 THEN NULL
+                 WHEN NOT completed THEN $6
+                 ELSE completed_at
+             END
+         WHERE id = $7 AND deleted_at IS NULL AND ($8::int IS NULL OR version = $8)
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(&update.title)
+    .bind(&update.description)
+    .bind(update.completed)
+    .bind(update.due_date)
+    .bind(update.priority)
+    .bind(Utc::now())
     .bind(id)
-    .bind(&req.title)
-    .bind(&req.description)
-    .bind(false)
-    .bind(now)
-    .bind(now)
-    .fetch_one(pool.get_ref())
+    .bind(update.expected_version)
+    .bind(update.recurrence)
+    .bind(&update.metadata)
+    .bind(&update.color)
+    .bind(update.estimated_minutes)
+    .bind(update.actual_minutes)
+    .fetch_optional(&mut *conn)
     .await?;
 
-    Ok(HttpResponse::Created().json(TodoResponse::from(todo)))
+    let Some(mut todo) = todo else {
+        return Ok(None);
+    };
+
+    if let Some(tags) = &update.tags {
+        todo.tags = replace_tags(conn, id, tags).await?;
+    }
+
+    Ok(Some(todo))
+}
+
+/// When an update completes a recurring todo, insert its next occurrence with the due date
+/// moved on by one interval. The recurrence moves to the new todo and the completed one stays
+/// behind as history, so un-completing and re-completing it does not spawn a second copy.
+async fn schedule_next_occurrence(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    was_completed: bool,
+    todo: &mut Todo,
+) -> Result<Option<Todo>, sqlx::Error> {
+    let Some(recurrence) = todo.recurrence else {
+        return Ok(None);
+    };
+    if was_completed || !todo.completed {
+        return Ok(None);
+    }
+
+    // Without a due date, count the interval from when it was completed
+    let from = todo.due_date.or(todo.completed_at).unwrap_or_else(Utc::now);
+    let next = NewTodo {
+        title: todo.title.clone(),
+        description: todo.description.clone(),
+        due_date: Some(recurrence.advance(from)),
+        priority: todo.priority,
+        tags: todo.tags.clone(),
+        parent_id: todo.parent_id,
+        recurrence: Some(recurrence),
+        metadata: todo.metadata.clone(),
+        color: todo.color.clone(),
+        estimated_minutes: todo.estimated_minutes,
+        actual_minutes: None,
+    };
+    let next = insert_todo(conn, user, &next).await?;
+
+    sqlx::query("UPDATE todos SET recurrence = NULL WHERE id = $1")
+        .bind(todo.id)
+        .execute(&mut *conn)
+        .await?;
+    todo.recurrence = None;
+
+    Ok(Some(next))
+}
+
+fn version_conflict(existing: &Todo) -> ApiError {
+    ApiError::Conflict(format!(
+        "Todo with id {} was modified by someone else; current version is {}",
+        existing.id, existing.version
+    ))
+}
+
+/// Answer an update that would leave `existing` as it is without writing it: a write would
+/// still bump `updated_at` and `version`, which ETags, delta sync and the history key off.
+/// A stale `version` is a conflict all the same.
+fn unchanged(existing: Todo, update: &TodoUpdate) -> Result<HttpResponse, ApiError> {
+    if update.expected_version.is_some_and(|version| version != existing.version) {
+        return Err(version_conflict(&existing));
+    }
+    let tag = etag(&existing);
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, tag.to_string()))
+        .json(TodoResponse::from(existing)))
+}
+
+/// Load a live todo owned by `user` and lock its row until the surrounding transaction ends
+async fn fetch_for_update(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    id: Uuid,
+) -> Result<Todo, ApiError> {
+    sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL
+         FOR UPDATE",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))
 }
 
-/// Update a todo
+/// Replace a todo with the given representation
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    request_body = ReplaceTodoRequest,
+    responses(
+        (status = 200, description = "Todo replaced", body = TodoResponse),
+        (status = 400, description = "Invalid request body", body = ErrorResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+        (status = 409, description = "Stale `version`, or a locked completed todo",
+            body = ErrorResponse),
+        (status = 412, description = "`If-Match` no longer matches", body = ErrorResponse),
+    )
+)]
 pub async fn update_todo(
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
-    req: web::Json<UpdateTodoRequest>,
+    req: web::Json<ReplaceTodoRequest>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let now = Utc::now();
+    let update = req.validate()?;
+
+    let mut tx = pool.begin().await?;
+    let existing = fetch_for_update(&mut tx, user, id).await?;
+    check_if_match(&http_req, &existing)?;
+    if !update.changes(&existing) {
+        return unchanged(existing, &update);
+    }
+    check_completed_lock(&http_req, &existing, &update)?;
+
+    // The row is locked and known to exist, so a miss here means a stale version
+    let mut todo = write_todo(&mut tx, id, &update)
+        .await?
+        .ok_or_else(|| version_conflict(&existing))?;
+    let next = schedule_next_occurrence(&mut tx, user, existing.completed, &mut todo).await?;
+    tx.commit().await?;
+
+    let tag = etag(&todo);
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Updated { todo: response.clone() });
+    if let Some(next) = next {
+        bus.publish(user, TodoEvent::Created { todo: next.into() });
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, tag.to_string()))
+        .json(response))
+}
+
+/// `PATCH` bodies of this type follow RFC 7386 instead of the plain JSON rules
+const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// Partially update a todo
+#[utoipa::path(
+    patch,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    request_body(
+        content = UpdateTodoRequest,
+        description = "Also accepted as `application/merge-patch+json` (RFC 7386)",
+    ),
+    responses(
+        (status = 200, description = "Todo updated", body = TodoResponse),
+        (status = 400, description = "Invalid request body", body = ErrorResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+        (status = 409, description = "Stale `version`, or a locked completed todo",
+            body = ErrorResponse),
+        (status = 412, description = "`If-Match` no longer matches", body = ErrorResponse),
+    )
+)]
+pub async fn patch_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    body: web::Json<serde_json::Value>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let req = if http_req.content_type() == MERGE_PATCH_CONTENT_TYPE {
+        UpdateTodoRequest::from_merge_patch(body.into_inner())?
+    } else {
+        UpdateTodoRequest::from_json(body.into_inner())?
+    };
+    let patch = req.validate()?;
+
+    let mut tx = pool.begin().await?;
+    let existing = fetch_for_update(&mut tx, user, id).await?;
+    check_if_match(&http_req, &existing)?;
 
-    // First, check if the todo exists
-    let existing = sqlx::query_as::<_, Todo>(
-        "SELECT id, title, description, completed, created_at, updated_at FROM todos WHERE id = $1"
+    // Update fields, keeping existing values if not provided
+    let update = patch.apply(&existing);
+    if !update.changes(&existing) {
+        return unchanged(existing, &update);
+    }
+    check_completed_lock(&http_req, &existing, &update)?;
+
+    // The row is locked and known to exist, so a miss here means a stale version
+    let mut todo = write_todo(&mut tx, id, &update)
+        .await?
+        .ok_or_else(|| version_conflict(&existing))?;
+    let next = schedule_next_occurrence(&mut tx, user, existing.completed, &mut todo).await?;
+    tx.commit().await?;
+
+    let tag = etag(&todo);
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Updated { todo: response.clone() });
+    if let Some(next) = next {
+        bus.publish(user, TodoEvent::Created { todo: next.into() });
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, tag.to_string()))
+        .json(response))
+}
+
+/// Flip a todo's `completed` flag in place, without sending a body
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/toggle",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "Todo toggled", body = TodoResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
     )
+)]
+pub async fn toggle_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let mut tx = pool.begin().await?;
+
+    // As in write_todo, `completed` on the right-hand side is the value before the flip
+    let mut todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET completed = NOT completed, updated_at = $1, version = version + 1,
+             completed_at = CASE WHEN completed THEN NULL ELSE $1 END
+         WHERE id = $2 AND user_id IS NOT DISTINCT FROM $3 AND deleted_at IS NULL
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(Utc::now())
     .bind(id)
-    .fetch_optional(pool.get_ref())
-    .await?;
+    .bind(user.0)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+    let next = schedule_next_occurrence(&mut tx, user, !todo.completed, &mut todo).await?;
+    tx.commit().await?;
 
-    if existing.is_none() {
-        return Err(ApiError::NotFound(format!("Todo with id {} not found", id)));
+    let tag = etag(&todo);
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Updated { todo: response.clone() });
+    if let Some(next) = next {
+        bus.publish(user, TodoEvent::Created { todo: next.into() });
     }
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, tag.to_string()))
+        .json(response))
+}
 
-    let existing = existing.unwrap();
+/// Positions of the live todos that would sit either side of a todo moved to `index`,
+/// with the todo itself left out of the list. An `index` past the end means the end.
+async fn neighbour_positions(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+    id: Uuid,
+    index: i64,
+) -> Result<(Option<f64>, Option<f64>), sqlx::Error> {
+    let others: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM todos
+         WHERE user_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL AND id <> $2"
+    )
+    .bind(user.0)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+    let index = index.min(others);
 
-    // Update fields, keeping existing values if not provided
-    let title = req.title.as_ref().unwrap_or(&existing.title).clone();
-    let description = req.description.as_ref().or(existing.description.as_ref()).cloned();
-    let completed = req.completed.unwrap_or(existing.completed);
+    let positions: Vec<f64> = sqlx::query_scalar(
+        "SELECT position FROM todos
+         WHERE user_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL AND id <> $2
+         ORDER BY position, id
+         OFFSET $3 LIMIT 2"
+    )
+    .bind(user.0)
+    .bind(id)
+    .bind((index - 1).max(0))
+    .fetch_all(&mut *conn)
+    .await?;
 
-    let todo = sqlx::query_as::<_, Todo>(
-        "UPDATE todos SET title = $1, description = $2, completed = $3, updated_at = $4
-         WHERE id = $5
-         RETURNING id, title, description, completed, created_at, updated_at"
+    Ok(match (index, positions.as_slice()) {
+        (0, [after, ..]) => (None, Some(*after)),
+        (_, [before, after]) => (Some(*before), Some(*after)),
+        (_, [before]) => (Some(*before), None),
+        _ => (None, None),
+    })
+}
+
+/// Space all of the user's todos, deleted ones included, `POSITION_STEP` apart in their
+/// current order
+async fn renumber_positions(conn: &mut PgConnection, user: CurrentUser) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE todos SET position = ranked.rank * $2
+         FROM (
+             SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS rank
+             FROM todos WHERE user_id IS NOT DISTINCT FROM $1
+         ) AS ranked
+         WHERE todos.id = ranked.id"
     )
-    .bind(title)
-    .bind(description)
-    .bind(completed)
-    .bind(now)
+    .bind(user.0)
+    .bind(POSITION_STEP)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// One move or reorder per user at a time, so two of them never pick the same gap
+async fn lock_positions(conn: &mut PgConnection, user: CurrentUser) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "SELECT pg_advisory_xact_lock(
+             hashtextextended('position:' || COALESCE($1::text, ''), 0)
+         )"
+    )
+    .bind(user.0)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Move a todo to `index` in the user's manual order
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}/position",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    request_body = MoveTodoRequest,
+    responses(
+        (status = 200, description = "Todo moved", body = TodoResponse),
+        (status = 400, description = "Negative `index`", body = ErrorResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn move_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    req: web::Json<MoveTodoRequest>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let index = req.validate()?;
+
+    let mut tx = pool.begin().await?;
+    lock_positions(&mut tx, user).await?;
+    fetch_for_update(&mut tx, user, id).await?;
+
+    let mut neighbours = neighbour_positions(&mut tx, user, id, index).await?;
+    if let (Some(before), Some(after)) = neighbours {
+        if after - before < MIN_POSITION_GAP {
+            renumber_positions(&mut tx, user).await?;
+            neighbours = neighbour_positions(&mut tx, user, id, index).await?;
+        }
+    }
+    let position = match neighbours {
+        (Some(before), Some(after)) => (before + after) / 2.0,
+        (Some(before), None) => before + POSITION_STEP,
+        (None, Some(after)) => after - POSITION_STEP,
+        (None, None) => POSITION_STEP,
+    };
+
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET position = $1, updated_at = $2, version = version + 1
+         WHERE id = $3
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(position)
+    .bind(Utc::now())
     .bind(id)
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
+
+    let tag = etag(&todo);
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Updated { todo: response.clone() });
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, tag.to_string()))
+        .json(response))
+}
 
-    Ok(HttpResponse::Ok().json(TodoResponse::from(todo)))
+/// The user's todos, deleted ones included, by manual order
+async fn current_positions(
+    conn: &mut PgConnection,
+    user: CurrentUser,
+) -> Result<Vec<(Uuid, f64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, position FROM todos WHERE user_id IS NOT DISTINCT FROM $1
+         ORDER BY position, id
+         FOR UPDATE"
+    )
+    .bind(user.0)
+    .fetch_all(&mut *conn)
+    .await
 }
 
-/// Delete a todo
+/// Put several todos in the given order in one transaction
+#[utoipa::path(
+    put,
+    path = "/api/todos/order",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    request_body = ReorderTodosRequest,
+    responses(
+        (status = 200, description = "The listed todos in their new order",
+         body = [TodoResponse]),
+        (status = 400, description = "Empty or repeated `ids`", body = ErrorResponse),
+        (status = 404, description = "A listed todo was not found", body = ErrorResponse),
+    )
+)]
+pub async fn reorder_todos(
+    pool: web::Data<PgPool>,
+    req: web::Json<ReorderTodosRequest>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    req.validate()?;
+    let ReorderTodosRequest { ids, unlisted } = req.into_inner();
+    let listed: HashSet<Uuid> = ids.iter().copied().collect();
+
+    let mut tx = pool.begin().await?;
+    lock_positions(&mut tx, user).await?;
+    let live: HashSet<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM todos
+         WHERE id = ANY($1) AND user_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL"
+    )
+    .bind(&ids)
+    .bind(user.0)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+    if let Some(missing) = ids.iter().find(|id| !live.contains(id)) {
+        return Err(ApiError::NotFound(format!("Todo with id {} not found", missing)));
+    }
+
+    let mut current = current_positions(&mut tx, user).await?;
+    // Only the listed todos are written; the others keep the positions they have
+    let targets: Vec<f64> = match unlisted {
+        Unlisted::Keep => {
+            let slots_of = |current: &[(Uuid, f64)]| -> Vec<f64> {
+                current
+                    .iter()
+                    .filter(|(id, _)| listed.contains(id))
+                    .map(|&(_, position)| position)
+                    .collect()
+            };
+            let mut slots = slots_of(&current);
+            // Todos sharing a position have no order of their own to hand over
+            if slots.windows(2).any(|pair| pair[1] - pair[0] < MIN_POSITION_GAP) {
+                renumber_positions(&mut tx, user).await?;
+                current = current_positions(&mut tx, user).await?;
+                slots = slots_of(&current);
+            }
+            slots
+        }
+        Unlisted::End => {
+            let first = current.first().map_or(0.0, |&(_, position)| position);
+            let count = ids.len() as f64;
+            (0..ids.len()).map(|index| first - (count - index as f64) * POSITION_STEP).collect()
+        }
+    };
+    let already_first = current.iter().map(|(id, _)| id).take(ids.len()).eq(ids.iter());
+    let old: HashMap<Uuid, f64> = current.into_iter().collect();
+    let moves: Vec<(Uuid, f64)> = if unlisted == Unlisted::End && already_first {
+        Vec::new()
+    } else {
+        ids.iter()
+            .zip(targets)
+            .filter(|&(id, position)| old.get(id) != Some(&position))
+            .map(|(&id, position)| (id, position))
+            .collect()
+    };
+
+    let (moved, positions): (Vec<Uuid>, Vec<f64>) = moves.into_iter().unzip();
+    if !moved.is_empty() {
+        sqlx::query(
+            "UPDATE todos SET position = moves.position, updated_at = $3, version = version + 1
+             FROM unnest($1::uuid[], $2::float8[]) AS moves(id, position)
+             WHERE todos.id = moves.id"
+        )
+        .bind(&moved)
+        .bind(&positions)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+    }
+    let todos = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos WHERE id = ANY($1) ORDER BY position, id",
+        TODO_COLUMNS
+    ))
+    .bind(&ids)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let todos: Vec<TodoResponse> = todos.into_iter().map(TodoResponse::from).collect();
+    for todo in todos.iter().filter(|todo| moved.contains(&todo.id)) {
+        bus.publish(user, TodoEvent::Updated { todo: todo.clone() });
+    }
+    Ok(HttpResponse::Ok().json(todos))
+}
+
+/// Every recorded version of a todo, oldest first, with the fields each change touched
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/history",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "The todo's versions, oldest first",
+         body = [TodoHistoryEntry]),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn todo_history(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+
+    // Soft-deleted todos keep their history, so it can still be read before a restore
+    let mut tx = pool.begin().await?;
+    let current = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+    let snapshots: Vec<serde_json::Value> =
+        sqlx::query_scalar("SELECT snapshot FROM todo_history WHERE todo_id = $1 ORDER BY id")
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+    tx.commit().await?;
+
+    let mut versions = snapshots
+        .into_iter()
+        .map(decode_snapshot)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| {
+            ApiError::InternalServerError(format!("Unreadable history for todo {}: {}", id, err))
+        })?;
+    versions.push(current);
+    Ok(HttpResponse::Ok().json(history_entries(versions)))
+}
+
+/// Soft-delete a todo, or remove it permanently with `?hard=true`.
+/// A todo with subtasks is only deleted with `?cascade=true`, which deletes the whole subtree.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID"), DeleteParams),
+    responses(
+        (status = 204, description = "Todo deleted"),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+        (status = 409, description = "Has subtasks and `cascade` is unset", body = ErrorResponse),
+    )
+)]
 pub async fn delete_todo(
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
+    params: web::Query<DeleteParams>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
+    let mut tx = pool.begin().await?;
+
+    // Lock the todo so no subtask can be added between the check and the delete.
+    // A hard delete may also remove an already soft-deleted todo.
+    let found: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM todos
+         WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2 AND ($3 OR deleted_at IS NULL)
+         FOR UPDATE"
+    )
+    .bind(id)
+    .bind(user.0)
+    .bind(params.hard)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if found.is_none() {
+        return Err(ApiError::NotFound(format!("Todo with id {} not found", id)));
+    }
 
-    let result = sqlx::query("DELETE FROM todos WHERE id = $1")
+    if !params.cascade {
+        // The foreign key would remove even soft-deleted subtasks on a hard delete
+        let children: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM todos WHERE parent_id = $1 AND ($2 OR deleted_at IS NULL)"
+        )
         .bind(id)
-        .execute(pool.get_ref())
+        .bind(params.hard)
+        .fetch_one(&mut *tx)
         .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(ApiError::NotFound(format!("Todo with id {} not found", id)));
+        if children > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Todo with id {} has {} subtask(s); pass cascade=true to delete them too",
+                id, children
+            )));
+        }
     }
 
+    let subtree = "WITH RECURSIVE subtree AS (
+                       SELECT id FROM todos WHERE id = $1
+                       UNION
+                       SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+                   )";
+    let deleted: Vec<Uuid> = if params.hard {
+        sqlx::query_scalar(&format!(
+            "{} DELETE FROM todos WHERE id IN (SELECT id FROM subtree) RETURNING id",
+            subtree
+        ))
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?
+    } else {
+        sqlx::query_scalar(&format!(
+            "{} UPDATE todos SET deleted_at = $2, updated_at = $2, version = version + 1
+             WHERE id IN (SELECT id FROM subtree) AND deleted_at IS NULL
+             RETURNING id",
+            subtree
+        ))
+        .bind(id)
+        .bind(Utc::now())
+        .fetch_all(&mut *tx)
+        .await?
+    };
+    tx.commit().await?;
+
+    for &id in &deleted {
+        bus.publish(user, TodoEvent::Deleted { id });
+    }
     Ok(HttpResponse::NoContent().finish())
 }
+
+/// List the live subtasks of a todo, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/subtasks",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Parent todo ID")),
+    responses(
+        (status = 200, description = "The subtasks", body = [TodoResponse]),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn list_subtasks(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let mut conn = pool.acquire().await?;
+    ensure_parent_exists(&mut conn, user, Some(id))
+        .await
+        .map_err(|_| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+
+    let subtasks = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE parent_id = $1 AND deleted_at IS NULL
+         ORDER BY created_at, id",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let response: Vec<TodoResponse> = subtasks.into_iter().map(|t| t.into()).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Create a pending copy of a todo
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/duplicate",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "ID of the todo to copy"), DuplicateParams),
+    responses(
+        (status = 201, description = "Copy created", body = TodoResponse,
+            headers(("Location" = String, description = "Path of the copy"))),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn duplicate_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    params: web::Query<DuplicateParams>,
+    http_req: HttpRequest,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let mut tx = pool.begin().await?;
+
+    let source = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT {} FROM todos
+         WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+
+    let todo = insert_todo(&mut tx, user, &source.duplicate(params.suffix)).await?;
+    enforce_todo_limit(&mut tx, &http_req, user).await?;
+    tx.commit().await?;
+
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Created { todo: response.clone() });
+    Ok(created(&http_req, &response).json(response))
+}
+
+/// Undo a soft delete
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/restore",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "Todo restored", body = TodoResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+        (status = 409, description = "Todo is not deleted, or restoring it would exceed the \
+                                      todo limit", body = ErrorResponse),
+    )
+)]
+pub async fn restore_todo(
+    pool: web::Data<PgPool>,
+    http_req: HttpRequest,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let mut tx = pool.begin().await?;
+
+    let restored = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET deleted_at = NULL, updated_at = $1, version = version + 1
+         WHERE id = $2 AND user_id IS NOT DISTINCT FROM $3 AND deleted_at IS NOT NULL
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(Utc::now())
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(todo) = restored {
+        // A restored todo counts against the limit again, like a new one
+        enforce_todo_limit(&mut tx, &http_req, user).await?;
+        tx.commit().await?;
+        // A restored todo reappears to subscribers as if newly created
+        let response = TodoResponse::from(todo);
+        bus.publish(user, TodoEvent::Created { todo: response.clone() });
+        return Ok(HttpResponse::Ok().json(response));
+    }
+
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM todos WHERE id = $1 AND user_id IS NOT DISTINCT FROM $2)"
+    )
+    .bind(id)
+    .bind(user.0)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if exists {
+        Err(ApiError::Conflict(format!("Todo with id {} is not deleted", id)))
+    } else {
+        Err(ApiError::NotFound(format!("Todo with id {} not found", id)))
+    }
+}
+
+/// Set the archived flag on a live todo owned by `user`
+async fn set_archived(
+    pool: &PgPool,
+    user: CurrentUser,
+    bus: &EventBus,
+    id: Uuid,
+    archived: bool,
+) -> Result<HttpResponse, ApiError> {
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET archived = $1, updated_at = $2, version = version + 1
+         WHERE id = $3 AND user_id IS NOT DISTINCT FROM $4 AND deleted_at IS NULL
+         RETURNING {}",
+        TODO_COLUMNS
+    ))
+    .bind(archived)
+    .bind(Utc::now())
+    .bind(id)
+    .bind(user.0)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Todo with id {} not found", id)))?;
+
+    let response = TodoResponse::from(todo);
+    bus.publish(user, TodoEvent::Updated { todo: response.clone() });
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Hide a todo from the default list without deleting it
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/archive",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "Todo archived", body = TodoResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn archive_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    set_archived(&pool, user, &bus, id.into_inner(), true).await
+}
+
+/// Return an archived todo to the default list
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/unarchive",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Todo ID")),
+    responses(
+        (status = 200, description = "Todo unarchived", body = TodoResponse),
+        (status = 404, description = "Todo not found", body = ErrorResponse),
+    )
+)]
+pub async fn unarchive_todo(
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    set_archived(&pool, user, &bus, id.into_inner(), false).await
+}
+
+/// Delete several todos by ID in one statement (soft unless `?hard=true`).
+/// Subtasks outside the batch block their parent's delete unless `?cascade=true`.
+#[utoipa::path(
+    post,
+    path = "/api/todos/batch-delete",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(DeleteParams, DryRunParams),
+    request_body = BatchDeleteRequest,
+    responses(
+        (status = 200, description = "Todos deleted, or listed by a dry run",
+         body = BatchDeleteResponse),
+        (status = 400, description = "Empty `ids`", body = ErrorResponse),
+        (status = 409, description = "A todo has subtasks outside the batch and `cascade` is unset",
+            body = ErrorResponse),
+    )
+)]
+pub async fn batch_delete_todos(
+    pool: web::Data<PgPool>,
+    req: web::Json<BatchDeleteRequest>,
+    params: web::Query<DeleteParams>,
+    dry_run: web::Query<DryRunParams>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    if req.ids.is_empty() {
+        return Err(ApiError::BadRequest("ids cannot be empty".to_string()));
+    }
+
+    let mut ids = req.into_inner().ids;
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut tx = pool.begin().await?;
+    // Lock the todos so no subtask can be added between the check and the delete.
+    // A hard delete also removes rows that are already soft-deleted.
+    let found: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM todos
+         WHERE id = ANY($1) AND user_id IS NOT DISTINCT FROM $2 AND ($3 OR deleted_at IS NULL)
+         ORDER BY id
+         FOR UPDATE"
+    )
+    .bind(&ids)
+    .bind(user.0)
+    .bind(params.hard)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let targets: Vec<Uuid> = if params.cascade {
+        sqlx::query_scalar(
+            "WITH RECURSIVE subtree AS (
+                 SELECT id FROM todos WHERE id = ANY($1)
+                 UNION
+                 SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+             )
+             SELECT id FROM subtree"
+        )
+        .bind(&found)
+        .fetch_all(&mut *tx)
+        .await?
+    } else {
+        // As for a single delete, subtasks that are not in the batch keep their parent from
+        // being deleted; the foreign key would take even soft-deleted ones on a hard delete
+        let parent: Option<Uuid> = sqlx::query_scalar(
+            "SELECT parent_id FROM todos
+             WHERE parent_id = ANY($1) AND id <> ALL($1) AND ($2 OR deleted_at IS NULL)
+             ORDER BY parent_id
+             LIMIT 1"
+        )
+        .bind(&found)
+        .bind(params.hard)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(parent) = parent {
+            return Err(ApiError::Conflict(format!(
+                "Todo with id {} has subtasks that are not being deleted; \
+                 pass cascade=true to delete them too",
+                parent
+            )));
+        }
+        found
+    };
+
+    if dry_run.dry_run {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            "SELECT {} FROM todos
+             WHERE id = ANY($1) AND ($2 OR deleted_at IS NULL)
+             ORDER BY created_at, id",
+            TODO_COLUMNS
+        ))
+        .bind(&targets)
+        .bind(params.hard)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        return Ok(HttpResponse::Ok().json(BatchDeleteResponse {
+            requested: ids.len(),
+            deleted: todos.len() as u64,
+            dry_run: true,
+            todos: Some(todos.into_iter().map(TodoResponse::from).collect()),
+        }));
+    }
+
+    let deleted: Vec<Uuid> = if params.hard {
+        sqlx::query_scalar("DELETE FROM todos WHERE id = ANY($1) RETURNING id")
+            .bind(&targets)
+            .fetch_all(&mut *tx)
+            .await?
+    } else {
+        sqlx::query_scalar(
+            "UPDATE todos SET deleted_at = $1, updated_at = $1, version = version + 1
+             WHERE id = ANY($2) AND deleted_at IS NULL
+             RETURNING id"
+        )
+        .bind(Utc::now())
+        .bind(&targets)
+        .fetch_all(&mut *tx)
+        .await?
+    };
+    tx.commit().await?;
+
+    for &id in &deleted {
+        bus.publish(user, TodoEvent::Deleted { id });
+    }
+
+    Ok(HttpResponse::Ok().json(BatchDeleteResponse {
+        requested: ids.len(),
+        deleted: deleted.len() as u64,
+        dry_run: false,
+        todos: None,
+    }))
+}
+
+/// Completed todos `purge_completed_todos` removes: those whose subtree holds no live
+/// pending todo, since deleting a parent takes its subtasks with it. `$1` is the user and
+/// `$2` whether the delete is hard, which also takes already soft-deleted ones.
+const PURGEABLE_COMPLETED: &str = "WITH RECURSIVE subtree AS (
+        SELECT id AS root, id FROM todos
+        WHERE completed AND user_id IS NOT DISTINCT FROM $1 AND ($2 OR deleted_at IS NULL)
+        UNION
+        SELECT subtree.root, todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+    ), purgeable AS (
+        SELECT subtree.root AS id FROM subtree JOIN todos ON todos.id = subtree.id
+        GROUP BY subtree.root
+        HAVING NOT bool_or(NOT todos.completed AND todos.deleted_at IS NULL)
+    )";
+
+/// Delete every completed todo in one statement (soft unless `?hard=true`)
+#[utoipa::path(
+    delete,
+    path = "/api/todos/completed",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(DeleteParams, DryRunParams),
+    responses(
+        (status = 200, description = "Completed todos deleted, or listed by a dry run",
+         body = PurgeCompletedResponse),
+    )
+)]
+pub async fn purge_completed_todos(
+    pool: web::Data<PgPool>,
+    params: web::Query<DeleteParams>,
+    dry_run: web::Query<DryRunParams>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    if dry_run.dry_run {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            "{} SELECT {} FROM todos WHERE id IN (SELECT id FROM purgeable)
+             ORDER BY created_at, id",
+            PURGEABLE_COMPLETED, TODO_COLUMNS
+        ))
+        .bind(user.0)
+        .bind(params.hard)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+        return Ok(HttpResponse::Ok().json(PurgeCompletedResponse {
+            deleted: todos.len() as u64,
+            dry_run: true,
+            todos: Some(todos.into_iter().map(TodoResponse::from).collect()),
+        }));
+    }
+
+    let deleted: Vec<Uuid> = if params.hard {
+        sqlx::query_scalar(&format!(
+            "{} DELETE FROM todos WHERE id IN (SELECT id FROM purgeable) RETURNING id",
+            PURGEABLE_COMPLETED
+        ))
+        .bind(user.0)
+        .bind(true)
+        .fetch_all(pool.get_ref())
+        .await?
+    } else {
+        sqlx::query_scalar(&format!(
+            "{} UPDATE todos SET deleted_at = $3, updated_at = $3, version = version + 1
+             WHERE id IN (SELECT id FROM purgeable)
+             RETURNING id",
+            PURGEABLE_COMPLETED
+        ))
+        .bind(user.0)
+        .bind(false)
+        .bind(Utc::now())
+        .fetch_all(pool.get_ref())
+        .await?
+    };
+
+    for &id in &deleted {
+        bus.publish(user, TodoEvent::Deleted { id });
+    }
+
+    Ok(HttpResponse::Ok().json(PurgeCompletedResponse {
+        deleted: deleted.len() as u64,
+        dry_run: false,
+        todos: None,
+    }))
+}
+
+/// Set `completed` on every todo matching the list filters in one statement, and schedule
+/// the next occurrence of any recurring todo this completes. A dry run only lists them.
+async fn set_all_completed(
+    pool: &PgPool,
+    user: CurrentUser,
+    bus: &EventBus,
+    filter: &TodoFilter,
+    completed: bool,
+    dry_run: bool,
+) -> Result<HttpResponse, ApiError> {
+    reject_changed_since(filter)?;
+    if dry_run {
+        let mut query =
+            QueryBuilder::<Postgres>::new(format!("SELECT {} FROM todos", TODO_COLUMNS));
+        push_filters(&mut query, user, filter)?;
+        query
+            .push(" AND completed <> ")
+            .push_bind(completed)
+            .push(" ORDER BY created_at, id");
+        let todos: Vec<Todo> = query.build_query_as().fetch_all(pool).await?;
+
+        return Ok(HttpResponse::Ok().json(CompleteAllResponse {
+            updated: todos.len() as u64,
+            dry_run: true,
+            todos: Some(todos.into_iter().map(TodoResponse::from).collect()),
+        }));
+    }
+
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    let mut query = QueryBuilder::<Postgres>::new("UPDATE todos SET completed = ");
+    query
+        .push_bind(completed)
+        .push(", updated_at = ")
+        .push_bind(now)
+        .push(", version = version + 1, completed_at = CASE WHEN ")
+        .push_bind(completed)
+        .push(" THEN ")
+        .push_bind(now)
+        .push(" END");
+    push_filters(&mut query, user, filter)?;
+    query
+        .push(" AND completed <> ")
+        .push_bind(completed)
+        .push(format!(" RETURNING {}", TODO_COLUMNS));
+    let todos: Vec<Todo> = query.build_query_as().fetch_all(&mut *tx).await?;
+
+    let mut updated = Vec::with_capacity(todos.len());
+    let mut created = Vec::new();
+    for mut todo in todos {
+        if let Some(next) = schedule_next_occurrence(&mut tx, user, !completed, &mut todo).await? {
+            created.push(next);
+        }
+        updated.push(todo);
+    }
+    tx.commit().await?;
+
+    let count = updated.len() as u64;
+    for todo in updated {
+        bus.publish(user, TodoEvent::Updated { todo: todo.into() });
+    }
+    for todo in created {
+        bus.publish(user, TodoEvent::Created { todo: todo.into() });
+    }
+
+    Ok(HttpResponse::Ok().json(CompleteAllResponse {
+        updated: count,
+        dry_run: false,
+        todos: None,
+    }))
+}
+
+/// Mark every todo matching the list filters as completed
+#[utoipa::path(
+    post,
+    path = "/api/todos/complete-all",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(TodoFilter, DryRunParams),
+    responses(
+        (status = 200, description = "Todos completed, or listed by a dry run",
+         body = CompleteAllResponse),
+    )
+)]
+pub async fn complete_all_todos(
+    pool: web::Data<PgPool>,
+    filter: web::Query<TodoFilter>,
+    params: web::Query<DryRunParams>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    set_all_completed(&pool, user, &bus, &filter, true, params.dry_run).await
+}
+
+/// Mark every todo matching the list filters as not completed
+#[utoipa::path(
+    post,
+    path = "/api/todos/incomplete-all",
+    tag = "todos",
+    security((), ("api_key" = []), ("bearer" = [])),
+    params(TodoFilter, DryRunParams),
+    responses(
+        (status = 200, description = "Todos marked incomplete, or listed by a dry run",
+         body = CompleteAllResponse),
+    )
+)]
+pub async fn incomplete_all_todos(
+    pool: web::Data<PgPool>,
+    filter: web::Query<TodoFilter>,
+    params: web::Query<DryRunParams>,
+    user: CurrentUser,
+    bus: web::Data<EventBus>,
+) -> Result<HttpResponse, ApiError> {
+    set_all_completed(&pool, user, &bus, &filter, false, params.dry_run).await
+}
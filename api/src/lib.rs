@@ -0,0 +1,11 @@
+// The server is built from this library so integration tests in `tests/` can assemble the app
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod events;
+pub mod handlers;
+pub mod logging;
+pub mod middleware;
+pub mod models;
+pub mod openapi;
+pub mod routes;
@@ -0,0 +1,72 @@
+use chrono::{SecondsFormat, Utc};
+use env_logger::{Builder, Env};
+use log::kv::{self, Key, Value, VisitSource};
+use serde_json::{Map, Value as Json};
+use std::io::Write;
+
+/// How log lines are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// env_logger's human-readable lines, with `Logger`'s access log
+    Text,
+    /// One JSON object per line, for log aggregators
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    /// `json` switches to JSON lines; `text` or an empty value keeps the default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "text" | "" => Ok(LogFormat::Text),
+            _ => Err(format!("LOG_FORMAT must be text or json, got '{}'", s)),
+        }
+    }
+}
+
+/// Install the global logger. `RUST_LOG` filters as before, defaulting to `info`.
+pub fn init(format: LogFormat) {
+    let mut builder = Builder::from_env(Env::default().default_filter_or("info"));
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let mut line = Map::new();
+            line.insert(
+                "timestamp".into(),
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true).into(),
+            );
+            line.insert("level".into(), record.level().as_str().into());
+            line.insert("target".into(), record.target().into());
+            line.insert("message".into(), record.args().to_string().into());
+            // Structured fields such as the access log's method and status go alongside
+            record
+                .key_values()
+                .visit(&mut Fields(&mut line))
+                .map_err(std::io::Error::other)?;
+            writeln!(buf, "{}", Json::Object(line))
+        });
+    }
+    builder.init();
+}
+
+/// Copies a record's key-value pairs into the JSON line, keeping numbers and booleans typed
+struct Fields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64() {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
@@ -1,46 +1,244 @@
-mod db;
-mod error;
-mod handlers;
-mod models;
-mod routes;
-
-use actix_web::{web, App, HttpServer, middleware};
-use actix_cors::Cors;
+use actix_web::middleware::{from_fn, Compress, Condition, Logger};
+use actix_web::{web, App, HttpMessage, HttpServer};
 use dotenv::dotenv;
-use env_logger::Env;
-use std::env;
+use todo_app::config::Config;
+use todo_app::logging::{self, LogFormat};
+use todo_app::{db, error, events, handlers, middleware, routes};
+
+/// Seconds in-flight requests get to finish after a shutdown signal
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// `Logger`'s default format with the request ID appended
+const ACCESS_LOG_FORMAT: &str =
+    r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T request_id=%{request_id}xi"#;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let addr = format!("127.0.0.1:{}", port);
+    // Checked before anything else starts, so every bad setting is reported in one go
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let log_format = config.log_format;
+    logging::init(log_format);
+    let addr = config.bind_address();
 
     // Establish database connection
-    let pool = db::establish_connection()
+    let pool = db::establish_connection(&config.database_url, &config.database)
         .await
         .expect("Failed to create pool");
 
-    log::info!("Starting server at http://{}", addr);
-    log::info!("Connected to database: {}", database_url);
-
-    HttpServer::new(move || {
-        // Configure CORS
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header();
-
-        App::new()
-            .app_data(web::Data::new(pool.clone()))
-            .wrap(cors)
-            .wrap(middleware::Logger::default())
-            .configure(routes::configure_routes)
+    if config.run_migrations {
+        // Serving against a half-migrated schema is worse than not starting at all
+        db::run_migrations(&pool)
+            .await
+            .expect("Failed to run database migrations");
+    }
+
+    log::info!("Starting server at http://{}{}", addr, config.api_base_path.as_str());
+    log::info!("Connected to database: {}", config.database_url);
+
+    let cors = config.cors.clone();
+    if cors.dev_mode {
+        log::warn!("DEV_MODE is set: CORS allows any origin");
+    } else if cors.allowed_origins.is_empty() {
+        log::warn!("ALLOWED_ORIGINS is empty: cross-origin browser requests will be rejected");
+    } else {
+        log::info!("CORS allowed origins: {}", cors.allowed_origins.join(", "));
+    }
+
+    let rate_limiter = config.rate_limit.map(|limit| {
+        web::Data::new(middleware::RateLimiter::new(limit.requests, limit.window))
+    });
+    match &rate_limiter {
+        Some(limiter) => log::info!(
+            "Rate limit: {} requests per {}s per client IP",
+            limiter.requests(),
+            limiter.window().as_secs()
+        ),
+        None => log::info!("Rate limiting disabled"),
+    }
+
+    let request_timeout = config.request_timeout.map(web::Data::new);
+    match &request_timeout {
+        Some(timeout) => log::info!("Request timeout: {}s", timeout.duration().as_secs()),
+        None => log::info!("Request timeout disabled"),
+    }
+
+    let body_logging = config.body_logging.map(web::Data::new);
+    if let Some(logging) = &body_logging {
+        log::warn!(
+            "LOG_BODIES is set: JSON request and response bodies are logged (up to {} bytes)",
+            logging.max_bytes()
+        );
+    }
+    let pretty_json = middleware::PrettyJson::from_env().map(web::Data::new);
+    if pretty_json.is_some() {
+        log::info!("PRETTY_JSON is set: JSON responses are indented unless ?pretty=false");
+    }
+
+    let api_key = middleware::ApiKey::from_env().map(web::Data::new);
+    if api_key.is_none() {
+        log::warn!("API_KEY is not set: /api/todos is open to anyone who can reach the server");
+    }
+
+    let jwt_auth = middleware::JwtAuth::from_env().map(web::Data::new);
+    if jwt_auth.is_none() {
+        log::info!("JWT_SECRET is not set: todos are not scoped to users");
+    }
+
+    let workers = config.workers;
+    log::info!("Workers: {}", workers);
+    log::info!(
+        "Keep-alive: {}s, client timeout: {}s",
+        config.keep_alive.as_secs(),
+        config.client_timeout.as_secs()
+    );
+
+    let event_bus = web::Data::new(events::EventBus::new());
+    // Changes made through other instances reach this one's live-update subscribers too
+    let relay = actix_web::rt::spawn(events::relay_notifications(
+        pool.clone(),
+        event_bus.clone().into_inner(),
+    ));
+    // Every instance runs the loop; an advisory lock keeps their passes from overlapping
+    let cleanup = config.cleanup.map(|cleanup| {
+        match cleanup.purge_deleted_after() {
+            Some(after) => log::info!(
+                "Cleanup every {}s, purging todos kept in the trash for more than {} days",
+                cleanup.interval().as_secs(),
+                after.num_days()
+            ),
+            None => log::info!("Cleanup every {}s", cleanup.interval().as_secs()),
+        }
+        actix_web::rt::spawn(db::run_cleanup(pool.clone(), cleanup))
+    });
+    if cleanup.is_none() {
+        log::info!("Cleanup disabled");
+    }
+    let idempotency_ttl = web::Data::new(config.idempotency_key_ttl);
+    let todo_limit = config.todo_limit.map(web::Data::new);
+    if let Some(limit) = &todo_limit {
+        log::info!("Todo limit: {} per user", limit.max_todos());
+    }
+    let completed_lock = handlers::CompletedLock::from_env().map(web::Data::new);
+    if completed_lock.is_some() {
+        log::info!("LOCK_COMPLETED is set: completed todos must be reopened before editing");
+    }
+    let metrics = web::Data::new(middleware::Metrics::new());
+
+    let api_base_path = config.api_base_path.clone();
+    let shutdown_bus = event_bus.clone();
+    let app_pool = pool.clone();
+    let server = HttpServer::new(move || {
+        let mut app = App::new()
+            .app_data(web::Data::new(app_pool.clone()))
+            .app_data(event_bus.clone())
+            .app_data(idempotency_ttl.clone())
+            .app_data(metrics.clone());
+        // Shared across workers so a client's budget doesn't depend on which worker serves it
+        if let Some(limiter) = &rate_limiter {
+            app = app.app_data(limiter.clone());
+        }
+        if let Some(limit) = &todo_limit {
+            app = app.app_data(limit.clone());
+        }
+        if let Some(lock) = &completed_lock {
+            app = app.app_data(lock.clone());
+        }
+        if let Some(timeout) = &request_timeout {
+            app = app.app_data(timeout.clone());
+        }
+        if let Some(logging) = &body_logging {
+            app = app.app_data(logging.clone());
+        }
+        if let Some(pretty) = &pretty_json {
+            app = app.app_data(pretty.clone());
+        }
+        if let Some(api_key) = &api_key {
+            app = app.app_data(api_key.clone());
+        }
+        if let Some(jwt_auth) = &jwt_auth {
+            app = app.app_data(jwt_auth.clone());
+        }
+
+        app
+            .app_data(error::json_config())
+            .app_data(web::PathConfig::default().error_handler(error::path_error_handler))
+            .wrap(from_fn(middleware::rate_limit))
+            // Outside the rate limiter so rejected requests are counted too
+            .wrap(from_fn(middleware::track_metrics))
+            .wrap(cors.build())
+            .wrap(from_fn(middleware::pretty_json))
+            .wrap(from_fn(middleware::log_bodies))
+            .wrap(from_fn(middleware::skip_small_bodies))
+            .wrap(Compress::default())
+            .wrap(Condition::new(
+                log_format == LogFormat::Text,
+                Logger::new(ACCESS_LOG_FORMAT).custom_request_replace("request_id", |req| {
+                    req.extensions()
+                        .get::<middleware::RequestId>()
+                        .map_or_else(|| "-".to_string(), |id| id.0.clone())
+                }),
+            ))
+            .wrap(Condition::new(
+                log_format == LogFormat::Json,
+                from_fn(middleware::access_log),
+            ))
+            // Registered last so it runs first and the ID exists before the logger reads it
+            .wrap(from_fn(middleware::request_id))
+            .configure(routes::configure_routes_at(api_base_path.clone()))
     })
+    .workers(workers)
+    .keep_alive(config.keep_alive)
+    .client_request_timeout(config.client_timeout)
+    .shutdown_timeout(SHUTDOWN_TIMEOUT_SECS)
+    // Handled below, so live-update clients hear about the shutdown before it starts
+    .disable_signals()
     .bind(&addr)?
-    .run()
-    .await
+    .run();
+
+    let handle = server.handle();
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        // Open WebSockets and event streams would otherwise hold the shutdown up until
+        // the timeout and then be cut off
+        log::info!("Shutting down, telling live-update clients to reconnect");
+        shutdown_bus.shutdown();
+        handle.stop(true).await;
+    });
+    server.await?;
+
+    // The listener has to be dropped while the runtime can still run its cleanup
+    relay.abort();
+    let _ = relay.await;
+    if let Some(cleanup) = cleanup {
+        cleanup.abort();
+        let _ = cleanup.await;
+    }
+    // `run` resolves once the workers have stopped, so no request still holds a connection
+    pool.close().await;
+    log::info!("Graceful shutdown complete");
+
+    Ok(())
+}
+
+/// Resolve on SIGINT or SIGTERM, the signals actix-web would otherwise stop gracefully on
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
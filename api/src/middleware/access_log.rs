@@ -0,0 +1,39 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error, HttpMessage,
+};
+use std::time::Instant;
+
+use super::RequestId;
+
+/// Log each request as structured fields for `LOG_FORMAT=json`. The text format keeps
+/// actix's `Logger`, which can only produce a preformatted string.
+pub async fn access_log<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error> {
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let started = Instant::now();
+
+    let res = next.call(req).await?;
+
+    let status = res.status().as_u16();
+    let latency_ms = started.elapsed().as_micros() as f64 / 1000.0;
+    let request_id = res
+        .request()
+        .extensions()
+        .get::<RequestId>()
+        .map_or_else(|| "-".to_string(), |id| id.0.clone());
+    log::info!(
+        method = method.as_str(),
+        path = path.as_str(),
+        status = status,
+        latency_ms = latency_ms,
+        request_id = request_id.as_str();
+        "{} {} {}", method, path, status
+    );
+    Ok(res)
+}
@@ -0,0 +1,73 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    middleware::Next,
+    web, Error, ResponseError,
+};
+use std::env;
+
+use crate::error::ApiError;
+
+pub const X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// The shared secret clients must present, read from `API_KEY`
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// `None` when `API_KEY` is unset or blank, which leaves the API open
+    pub fn from_env() -> Option<Self> {
+        env::var("API_KEY")
+            .ok()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .map(ApiKey)
+    }
+
+    /// Compare without returning early, so response timing doesn't reveal how much matched
+    fn matches(&self, candidate: &str) -> bool {
+        let expected = self.0.as_bytes();
+        let candidate = candidate.as_bytes();
+        expected.len() == candidate.len()
+            && expected
+                .iter()
+                .zip(candidate)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// The key from `X-API-Key`, or from `Authorization: Bearer <key>`
+fn presented_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(key) = headers.get(&X_API_KEY).and_then(|v| v.to_str().ok()) {
+        return Some(key);
+    }
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Reject requests without the `ApiKey` registered as app data with 401.
+/// Wrap only the scopes that need protecting; with no key registered every request passes.
+pub async fn require_api_key<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if let Some(api_key) = req.app_data::<web::Data<ApiKey>>() {
+        let rejection = match presented_key(&req) {
+            Some(key) if api_key.matches(key.trim()) => None,
+            Some(_) => Some("Invalid API key"),
+            None => Some("Missing API key; send it as X-API-Key or Authorization: Bearer"),
+        };
+        if let Some(message) = rejection {
+            let mut res = ApiError::Unauthorized(message.to_string()).error_response();
+            res.headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
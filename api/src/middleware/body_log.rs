@@ -0,0 +1,121 @@
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::header::{HeaderMap, CONTENT_TYPE},
+    middleware::Next,
+    web::{self, Bytes},
+    Error, HttpMessage,
+};
+use serde_json::Value;
+
+use super::RequestId;
+
+pub(crate) const DEFAULT_LOG_BODIES_MAX_BYTES: usize = 4096;
+
+/// JSON keys whose values are replaced before a body is logged, at any depth.
+/// Add new sensitive fields here as the API grows them.
+const REDACTED_FIELDS: &[&str] = &["password", "token", "secret", "api_key", "authorization"];
+
+/// Logs request and response bodies for debugging client integrations
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLogging {
+    max_bytes: usize,
+}
+
+impl BodyLogging {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// The body as it should appear in the log: redacted when it parses as JSON,
+    /// and cut to `max_bytes`
+    fn render(&self, body: &[u8]) -> String {
+        let mut text = match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                redact(&mut json);
+                json.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        };
+
+        if text.len() > self.max_bytes {
+            let mut end = self.max_bytes;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push_str(&format!("... ({} bytes)", body.len()));
+        }
+        text
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *value = Value::String("[REDACTED]".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Streams (CSV export, live updates) are never buffered, so only JSON bodies are logged
+pub(super) fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// Log JSON request and response bodies when `BodyLogging` is registered. Each body is read
+/// into memory and handed on unchanged, so only enable this while debugging.
+pub async fn log_bodies<B: MessageBody + 'static>(
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(logging) = req.app_data::<web::Data<BodyLogging>>().map(|l| *l.get_ref()) else {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    };
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map_or_else(|| "-".to_string(), |id| id.0.clone());
+
+    if is_json(req.headers()) {
+        let body = req.extract::<Bytes>().await?;
+        log::info!(
+            request_id = request_id.as_str();
+            "Request body: {} {} {}", req.method(), req.path(), logging.render(&body)
+        );
+        // The handler still needs to read it
+        req.set_payload(Payload::from(body));
+    }
+
+    let res = next.call(req).await?;
+    if !is_json(res.headers()) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (http_req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = body::to_bytes(body)
+        .await
+        .map_err(|err| ErrorInternalServerError(err.into()))?;
+    log::info!(
+        request_id = request_id.as_str();
+        "Response body: {} {}", res.status().as_u16(), logging.render(&body)
+    );
+    Ok(ServiceResponse::new(http_req, res.set_body(BoxBody::new(body))))
+}
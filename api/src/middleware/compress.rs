@@ -0,0 +1,30 @@
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderValue, CONTENT_ENCODING},
+    middleware::Next,
+    Error,
+};
+
+/// Responses shorter than this go out uncompressed: the saving on a single todo is a few
+/// bytes, and not worth the CPU
+const MIN_COMPRESSED_BYTES: u64 = 1024;
+
+/// Mark small fixed-size responses `Content-Encoding: identity`, which actix's `Compress`
+/// leaves alone, so this must run inside it. Streamed bodies (CSV export, live updates) have
+/// no size up front and are still compressed, flushed whenever the stream pauses.
+pub async fn skip_small_bodies<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error> {
+    let mut res = next.call(req).await?;
+    let small = matches!(
+        res.response().body().size(),
+        BodySize::Sized(len) if len < MIN_COMPRESSED_BYTES
+    );
+    if small && !res.headers().contains_key(CONTENT_ENCODING) {
+        res.headers_mut()
+            .insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+    }
+    Ok(res)
+}
@@ -0,0 +1,78 @@
+use actix_cors::Cors;
+use actix_web::http::{header, Method};
+use std::env;
+
+use super::api_key::X_API_KEY;
+use crate::handlers::todo::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use super::request_id::X_REQUEST_ID;
+
+/// Cross-origin policy, read once at startup from `ALLOWED_ORIGINS` and `DEV_MODE`
+#[derive(Debug, Clone)]
+pub struct CorsSettings {
+    /// Allow every origin, method and header; only meant for local development
+    pub dev_mode: bool,
+    pub allowed_origins: Vec<String>,
+}
+
+impl CorsSettings {
+    pub fn from_env() -> Self {
+        let dev_mode = env::var("DEV_MODE")
+            .map(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        let allowed_origins = env::var("ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+
+        Self { dev_mode, allowed_origins }
+    }
+
+    /// Build the middleware; `App` is rebuilt per worker, so this runs once per worker
+    pub fn build(&self) -> Cors {
+        // Headers browsers may read from our responses
+        let exposed = [
+            header::ETAG,
+            header::LINK,
+            header::LOCATION,
+            header::HeaderName::from_static("x-total-count"),
+            header::HeaderName::from_static("x-next-cursor"),
+            header::HeaderName::from_static("x-pending-count"),
+            header::HeaderName::from_static("x-completed-count"),
+            header::HeaderName::from_static("x-sync-watermark"),
+            X_REQUEST_ID,
+            IDEMPOTENT_REPLAYED,
+        ];
+
+        if self.dev_mode {
+            return Cors::default()
+                .allow_any_origin()
+                .allow_any_method()
+                .allow_any_header()
+                .expose_headers(exposed);
+        }
+
+        self.allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+            .allowed_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
+            .allowed_headers([
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                header::IF_MATCH,
+                header::IF_NONE_MATCH,
+                X_API_KEY,
+                X_REQUEST_ID,
+                IDEMPOTENCY_KEY,
+            ])
+            .expose_headers(exposed)
+            .max_age(3600)
+    }
+}
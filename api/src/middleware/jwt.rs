@@ -0,0 +1,99 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest, ResponseError,
+};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::env;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// Verifies HS256 bearer tokens signed with `JWT_SECRET`
+pub struct JwtAuth {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    /// The user ID; `exp` is checked by `Validation`
+    sub: String,
+}
+
+impl JwtAuth {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: DecodingKey::from_secret(secret.as_bytes()),
+            validation: Validation::new(Algorithm::HS256),
+        }
+    }
+
+    /// `None` when `JWT_SECRET` is unset or blank, which turns per-user scoping off
+    pub fn from_env() -> Option<Self> {
+        env::var("JWT_SECRET")
+            .ok()
+            .filter(|secret| !secret.trim().is_empty())
+            .map(|secret| Self::new(&secret))
+    }
+
+    /// The user ID in a valid token, or the reason it was rejected
+    fn user_id(&self, token: &str) -> Result<Uuid, &'static str> {
+        let claims = decode::<Claims>(token, &self.key, &self.validation)
+            .map_err(|_| "Invalid or expired token")?
+            .claims;
+        claims.sub.parse().map_err(|_| "Token subject is not a valid user ID")
+    }
+}
+
+/// The user a request acts for, set by `authenticate`.
+/// `None` when JWT authentication is disabled, in which case only unowned todos are visible.
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentUser(pub Option<Uuid>);
+
+impl FromRequest for CurrentUser {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(req.extensions().get::<CurrentUser>().copied().unwrap_or(CurrentUser(None))))
+    }
+}
+
+/// Require a valid `Authorization: Bearer <jwt>` when a `JwtAuth` is registered as app data,
+/// recording its subject as the `CurrentUser`. With none registered every request passes.
+pub async fn authenticate<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if let Some(jwt) = req.app_data::<web::Data<JwtAuth>>() {
+        let token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        let user_id = match token {
+            Some(token) => jwt.user_id(token.trim()),
+            None => Err("Missing bearer token"),
+        };
+        match user_id {
+            Ok(user_id) => {
+                req.extensions_mut().insert(CurrentUser(Some(user_id)));
+            }
+            Err(message) => {
+                let mut res = ApiError::Unauthorized(message.to_string()).error_response();
+                res.headers_mut()
+                    .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+                return Ok(req.into_response(res).map_into_right_body());
+            }
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
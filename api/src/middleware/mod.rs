@@ -0,0 +1,23 @@
+pub mod access_log;
+pub mod api_key;
+pub mod body_log;
+pub mod compress;
+pub mod cors;
+pub mod jwt;
+pub mod metrics;
+pub mod pretty;
+pub mod rate_limit;
+pub mod request_id;
+pub mod timeout;
+
+pub use access_log::access_log;
+pub use api_key::{require_api_key, ApiKey};
+pub use body_log::{log_bodies, BodyLogging};
+pub use compress::skip_small_bodies;
+pub use cors::CorsSettings;
+pub use jwt::{authenticate, CurrentUser, JwtAuth};
+pub use metrics::{track_metrics, Metrics};
+pub use pretty::{pretty_json, PrettyJson};
+pub use rate_limit::{rate_limit, RateLimit, RateLimiter};
+pub use request_id::{request_id, RequestId};
+pub use timeout::{request_timeout, RequestTimeout};
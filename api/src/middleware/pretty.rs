@@ -0,0 +1,118 @@
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    middleware::Next,
+    web, Error, ResponseError,
+};
+use serde::Deserialize;
+use std::env;
+
+use super::body_log::is_json;
+use crate::error::ApiError;
+
+/// Pretty-print every JSON response unless the request asks otherwise; registered when
+/// `PRETTY_JSON` is set
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyJson;
+
+impl PrettyJson {
+    /// `Some` only when `PRETTY_JSON` is set to true
+    pub fn from_env() -> Option<Self> {
+        env::var("PRETTY_JSON")
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .then_some(Self)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PrettyParams {
+    pretty: Option<bool>,
+}
+
+/// Indent JSON responses when the request has `?pretty=true`, or when `PrettyJson` is
+/// registered and it doesn't have `?pretty=false`. Compact responses pass straight through;
+/// pretty ones are read into memory, so this must run inside the compression middleware.
+pub async fn pretty_json<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let default = req.app_data::<web::Data<PrettyJson>>().is_some();
+    let pretty = match web::Query::<PrettyParams>::from_query(req.query_string()) {
+        Ok(params) => params.pretty.unwrap_or(default),
+        Err(_) => {
+            let res = ApiError::BadRequest("pretty must be true or false".to_string());
+            return Ok(req.into_response(res.error_response()));
+        }
+    };
+
+    let res = next.call(req).await?;
+    if !pretty || !is_json(res.headers()) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (http_req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = body::to_bytes(body)
+        .await
+        .map_err(|err| ErrorInternalServerError(err.into()))?;
+    Ok(ServiceResponse::new(http_req, res.set_body(BoxBody::new(indent(&body)))))
+}
+
+/// Re-indent JSON the way `serde_json::to_string_pretty` writes it. Going through
+/// `serde_json::Value` instead would sort the keys of every object.
+fn indent(json: &[u8]) -> Vec<u8> {
+    fn new_line(out: &mut Vec<u8>, depth: usize) {
+        out.push(b'\n');
+        out.resize(out.len() + depth * 2, b' ');
+    }
+
+    let mut out = Vec::with_capacity(json.len() * 2);
+    let mut depth: usize = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut rest = json.iter().copied().peekable();
+    while let Some(byte) = rest.next() {
+        if in_string {
+            out.push(byte);
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => {
+                in_string = true;
+                out.push(byte);
+            }
+            b'{' | b'[' => {
+                out.push(byte);
+                while rest.next_if(u8::is_ascii_whitespace).is_some() {}
+                // Empty objects and arrays stay on one line
+                let close = if byte == b'{' { b'}' } else { b']' };
+                if let Some(close) = rest.next_if_eq(&close) {
+                    out.push(close);
+                } else {
+                    depth += 1;
+                    new_line(&mut out, depth);
+                }
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                new_line(&mut out, depth);
+                out.push(byte);
+            }
+            b',' => {
+                out.push(byte);
+                new_line(&mut out, depth);
+            }
+            b':' => out.extend_from_slice(b": "),
+            _ if byte.is_ascii_whitespace() => {}
+            _ => out.push(byte),
+        }
+    }
+    out
+}
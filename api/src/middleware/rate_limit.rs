@@ -0,0 +1,130 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderValue, RETRY_AFTER},
+    middleware::Next,
+    web, Error, ResponseError,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::ApiError;
+use crate::routes::ApiBasePath;
+
+pub(crate) const DEFAULT_RATE_LIMIT_REQUESTS: u32 = 60;
+pub(crate) const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+/// Paths that are never limited, so health probes and scrapes keep working under load.
+/// They are matched below the `ApiBasePath`.
+const EXEMPT_PATHS: &[&str] = &["/health", "/metrics"];
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct State {
+    buckets: HashMap<IpAddr, Bucket>,
+    last_sweep: Instant,
+}
+
+/// `RATE_LIMIT_REQUESTS` per `RATE_LIMIT_WINDOW_SECS`, as read by `Config::from_env`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub window: Duration,
+}
+
+/// In-process token bucket per client IP: each client may burst up to `capacity`
+/// requests, and tokens refill continuously at `capacity` per window
+pub struct RateLimiter {
+    capacity: f64,
+    window: Duration,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(requests: u32, window: Duration) -> Self {
+        Self {
+            capacity: f64::from(requests),
+            window,
+            state: Mutex::new(State {
+                buckets: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn requests(&self) -> u32 {
+        self.capacity as u32
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Take a token for `ip`, or return how long until the next one is available
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let refill_per_sec = self.capacity / self.window.as_secs_f64();
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // A bucket idle for a whole window is full again, which is the same as having none
+        if now.duration_since(state.last_sweep) >= self.window {
+            let window = self.window;
+            state.buckets.retain(|_, bucket| now.duration_since(bucket.updated) < window);
+            state.last_sweep = now;
+        }
+
+        let bucket = state.buckets.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(self.capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    }
+}
+
+/// Reject clients that exceed the `RateLimiter` registered as app data with 429.
+/// Requests pass straight through when no limiter is registered.
+pub async fn rate_limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let ip = req.peer_addr().map(|addr| addr.ip());
+
+    let exempt = req
+        .app_data::<web::Data<ApiBasePath>>()
+        .map_or(Some(req.path()), |base| base.strip(req.path()))
+        .is_some_and(|path| EXEMPT_PATHS.contains(&path));
+
+    if let (Some(limiter), Some(ip)) = (limiter, ip) {
+        if !exempt {
+            if let Err(wait) = limiter.check(ip) {
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                let mut res = ApiError::TooManyRequests(format!(
+                    "Rate limit of {} requests per {}s exceeded; retry in {}s",
+                    limiter.requests(),
+                    limiter.window().as_secs(),
+                    retry_after
+                ))
+                .error_response();
+                res.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+                return Ok(req.into_response(res).map_into_right_body());
+            }
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
@@ -0,0 +1,54 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error, ResponseError,
+};
+use std::time::Duration;
+
+use crate::error::ApiError;
+
+pub(crate) const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// How long a handler may take to produce its response
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeout(Duration);
+
+impl RequestTimeout {
+    pub fn new(timeout: Duration) -> Self {
+        Self(timeout)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+/// Answer `504` once a request runs past its `RequestTimeout`. The handler's future is
+/// dropped, which cancels it and rolls back any transaction it had open. Only the time to
+/// the response head counts, so streaming bodies (CSV export, live updates) are not cut off.
+///
+/// Keeping a handle to the request while routing is still ahead makes the router panic,
+/// so this wraps individual routes rather than the whole app.
+pub async fn request_timeout<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(timeout) = req.app_data::<web::Data<RequestTimeout>>().map(|t| t.duration()) else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+
+    // `next.call` consumes the request, so keep a handle to answer with on timeout
+    let http_req = req.request().clone();
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(res) => res.map(ServiceResponse::map_into_left_body),
+        Err(_) => {
+            let res = ApiError::GatewayTimeout(format!(
+                "Request did not complete within {}s",
+                timeout.as_secs_f64()
+            ))
+            .error_response();
+            Ok(ServiceResponse::new(http_req, res).map_into_right_body())
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::todo::{Todo, TodoResponse, TODO_FIELDS};
+
+/// Keys that change with every write, so listing them would add nothing
+const UNTRACKED_FIELDS: &[&str] = &["updated_at", "version", "child_count"];
+
+/// One version of a todo and how it differs from the one before
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TodoHistoryEntry {
+    pub version: i32,
+    /// When this version was written: the todo's `updated_at` at the time
+    pub changed_at: DateTime<Utc>,
+    /// Each field that differs from the previous version; empty for the oldest one
+    pub changes: BTreeMap<String, FieldChange>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldChange {
+    #[schema(value_type = Object)]
+    pub old: Value,
+    #[schema(value_type = Object)]
+    pub new: Value,
+}
+
+/// Read a `todo_history` snapshot, which is the row as JSON with its tags added
+pub fn decode_snapshot(mut snapshot: Value) -> Result<Todo, serde_json::Error> {
+    if let Value::Object(row) = &mut snapshot {
+        // Not a column; it has no meaning for a past version and is never compared
+        row.insert("child_count".to_string(), 0.into());
+    }
+    serde_json::from_value(snapshot)
+}
+
+/// Describe a todo's versions, given oldest first and ending with its current state
+pub fn history_entries(versions: Vec<Todo>) -> Vec<TodoHistoryEntry> {
+    let versions: Vec<(i32, DateTime<Utc>, Value)> = versions
+        .into_iter()
+        .map(|todo| {
+            let response = TodoResponse::from(todo);
+            let (version, changed_at) = (response.version, response.updated_at);
+            let json = serde_json::to_value(response).expect("TodoResponse serializes to JSON");
+            (version, changed_at, json)
+        })
+        .collect();
+
+    let mut previous: Option<&Value> = None;
+    let mut entries = Vec::with_capacity(versions.len());
+    for (version, changed_at, json) in &versions {
+        let mut changes = BTreeMap::new();
+        if let Some(previous) = previous {
+            for &field in TODO_FIELDS.iter().filter(|f| !UNTRACKED_FIELDS.contains(f)) {
+                if previous[field] != json[field] {
+                    let change = FieldChange {
+                        old: previous[field].clone(),
+                        new: json[field].clone(),
+                    };
+                    changes.insert(field.to_string(), change);
+                }
+            }
+        }
+        entries.push(TodoHistoryEntry { version: *version, changed_at: *changed_at, changes });
+        previous = Some(json);
+    }
+    entries
+}
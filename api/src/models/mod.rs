@@ -1,3 +1,16 @@
+pub mod health;
+pub mod history;
+pub mod pagination;
 pub mod todo;
 
-pub use todo::{Todo, CreateTodoRequest, UpdateTodoRequest, TodoResponse};
+pub use health::HealthResponse;
+pub use history::{decode_snapshot, history_entries, FieldChange, TodoHistoryEntry};
+pub use pagination::{cursor_link_header, link_header, Cursor, PaginationParams};
+pub use todo::{
+    Priority, Recurrence, Todo, CreateTodoRequest, CreateParams, NewTodo, ReplaceTodoRequest,
+    UpdateTodoRequest, TodoUpdate, TodoResponse, TodoFilter, DeleteParams, BatchDeleteRequest,
+    BatchDeleteResponse, PurgeCompletedResponse, CompleteAllResponse, SortParams, TodoStats,
+    TodoBoard, TodoStamp, AutocompleteParams, TodoSuggestion,
+    ImportTodoRequest, ImportedTodo, ImportParams, ImportResponse, OnConflict, MoveTodoRequest,
+    ReorderTodosRequest, Unlisted, OutputParams, DryRunParams, DuplicateParams, TODO_FIELDS,
+};
@@ -0,0 +1,140 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PaginationParams {
+    /// Page size (default 20, capped at 100)
+    pub limit: Option<i64>,
+    /// Number of todos to skip (default 0)
+    pub offset: Option<i64>,
+    /// `X-Next-Cursor` from the previous page, to page by position in the list instead of
+    /// by `offset`; only with `sort_by=created_at`
+    pub cursor: Option<String>,
+}
+
+impl PaginationParams {
+    /// Validate the requested page and return the effective `(limit, offset)`.
+    /// Limits above `MAX_PAGE_LIMIT` are capped rather than rejected.
+    pub fn resolve(&self) -> Result<(i64, i64), ApiError> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let offset = self.offset.unwrap_or(0);
+
+        if limit <= 0 {
+            return Err(ApiError::BadRequest("Limit must be greater than 0".to_string()));
+        }
+        if offset < 0 {
+            return Err(ApiError::BadRequest("Offset cannot be negative".to_string()));
+        }
+
+        Ok((limit.min(MAX_PAGE_LIMIT), offset))
+    }
+
+    /// The decoded `cursor`, if one was given. It replaces `offset`, so the two cannot be
+    /// combined.
+    pub fn cursor(&self) -> Result<Option<Cursor>, ApiError> {
+        let Some(cursor) = self.cursor.as_deref() else {
+            return Ok(None);
+        };
+        if self.offset.is_some() {
+            return Err(ApiError::BadRequest(
+                "cursor and offset cannot be combined".to_string(),
+            ));
+        }
+        Cursor::decode(cursor).map(Some)
+    }
+}
+
+/// Where a page ends in the `(created_at, id)` order. Rows added or removed elsewhere in the
+/// list don't shift it, unlike an offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Opaque to clients: URL-safe base64 of the timestamp in microseconds and the ID
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}_{}", self.created_at.timestamp_micros(), self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, ApiError> {
+        let invalid = || ApiError::BadRequest(format!("Invalid cursor '{}'", cursor));
+        let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (micros, id) = decoded.split_once('_').ok_or_else(invalid)?;
+        let created_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+        Ok(Cursor { created_at, id })
+    }
+}
+
+/// Query parameters other than the ones in `skip`, as raw `key=value` pairs
+fn other_params<'a>(query: &'a str, skip: &[&str]) -> Vec<&'a str> {
+    query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && !skip.contains(&key)
+        })
+        .collect()
+}
+
+/// Build an RFC 8288 `Link` header value for a page of `total` items.
+/// `query` is the request's raw query string; its other parameters (filters, sort)
+/// are carried over so every link points at the same result set.
+pub fn link_header(path: &str, query: &str, limit: i64, offset: i64, total: i64) -> String {
+    let base = other_params(query, &["limit", "offset"]);
+    let link = |page_offset: i64, rel: &str| {
+        let mut params = base.clone();
+        let page = format!("limit={}&offset={}", limit, page_offset);
+        params.push(&page);
+        format!("<{}?{}>; rel=\"{}\"", path, params.join("&"), rel)
+    };
+
+    let last_offset = if total > 0 { (total - 1) / limit * limit } else { 0 };
+    let mut links = vec![link(0, "first")];
+    if offset > 0 {
+        links.push(link((offset - limit).max(0), "prev"));
+    }
+    if offset + limit < total {
+        links.push(link(offset + limit, "next"));
+    }
+    links.push(link(last_offset, "last"));
+    links.join(", ")
+}
+
+/// The `Link` header for a page fetched by cursor: where a cursor walk starts, and the
+/// page after this one when there is one
+pub fn cursor_link_header(path: &str, query: &str, limit: i64, next: Option<&Cursor>) -> String {
+    let base = other_params(query, &["limit", "offset", "cursor"]);
+    let link = |cursor: Option<&Cursor>, rel: &str| {
+        let mut params = base.clone();
+        let page = match cursor {
+            Some(cursor) => format!("limit={}&cursor={}", limit, cursor.encode()),
+            None => format!("limit={}", limit),
+        };
+        params.push(&page);
+        format!("<{}?{}>; rel=\"{}\"", path, params.join("&"), rel)
+    };
+
+    let mut links = vec![link(None, "first")];
+    if let Some(next) = next {
+        links.push(link(Some(next), "next"));
+    }
+    links.join(", ")
+}
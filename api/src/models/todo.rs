@@ -1,38 +1,1308 @@
-use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Duration, Months, Utc};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+use crate::error::{ApiError, ValidationErrors};
+use crate::models::pagination::MAX_PAGE_LIMIT;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "todo_priority", rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl std::str::FromStr for Priority {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            _ => Err(ApiError::BadRequest(format!(
+                "Invalid priority '{}'; expected one of low, medium, high",
+                s
+            ))),
+        }
+    }
+}
+
+/// How often a todo comes back once it is completed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "todo_recurrence", rename_all = "lowercase")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    /// The same moment one interval later; months keep the day where they can and
+    /// clamp it otherwise (Jan 31 becomes Feb 28)
+    pub fn advance(self, from: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Recurrence::Daily => from + Duration::days(1),
+            Recurrence::Weekly => from + Duration::weeks(1),
+            Recurrence::Monthly => from.checked_add_months(Months::new(1)).unwrap_or(from),
+        }
+    }
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "daily" => Ok(Recurrence::Daily),
+            "weekly" => Ok(Recurrence::Weekly),
+            "monthly" => Ok(Recurrence::Monthly),
+            _ => Err(ApiError::BadRequest(format!(
+                "Invalid recurrence '{}'; expected one of daily, weekly, monthly",
+                s
+            ))),
+        }
+    }
+}
+
+/// Matches the `VARCHAR(255)` title column
+pub const MAX_TITLE_LENGTH: usize = 255;
+pub const MAX_DESCRIPTION_LENGTH: usize = 10_000;
+
+/// Tidy a title for storage: surrounding whitespace is dropped and every run of inner
+/// whitespace, tabs and newlines included, becomes a single space
+fn normalize_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Check a title, returning it normalized for storage
+fn validate_title(errors: &mut ValidationErrors, title: &str) -> String {
+    let title = normalize_title(title);
+    if title.is_empty() {
+        errors.add("title", "Title cannot be empty");
+    } else if title.chars().any(char::is_control) {
+        errors.add("title", "Title cannot contain control characters");
+    } else if title.chars().count() > MAX_TITLE_LENGTH {
+        errors.add(
+            "title",
+            format!("Title cannot be longer than {} characters", MAX_TITLE_LENGTH),
+        );
+    }
+    title
+}
+
+pub const MAX_TAG_LENGTH: usize = 50;
+
+/// The one spelling a tag is stored and filtered in: trimmed and lowercase
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Normalize every tag, dropping blank ones and duplicates, so `["Work", " work "]` is
+/// stored as `["work"]`. The result is sorted, as tags are always returned.
+fn validate_tags(errors: &mut ValidationErrors, tags: &[String]) -> Vec<String> {
+    let mut normalized = Vec::with_capacity(tags.len());
+    for (index, tag) in tags.iter().enumerate() {
+        let tag = normalize_tag(tag);
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            errors.add(
+                format!("tags[{}]", index),
+                format!("Tag cannot be longer than {} characters", MAX_TAG_LENGTH),
+            );
+        } else if !tag.is_empty() {
+            normalized.push(tag);
+        }
+    }
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+fn validate_description(errors: &mut ValidationErrors, description: Option<&str>) {
+    if description.is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        errors.add(
+            "description",
+            format!("Description cannot be longer than {} characters", MAX_DESCRIPTION_LENGTH),
+        );
+    }
+}
+
+/// Colors a todo may be given by name instead of as `#RRGGBB`
+pub const NAMED_COLORS: &[&str] =
+    &["red", "orange", "yellow", "green", "blue", "purple", "pink", "brown", "gray"];
+
+/// Check a `#RRGGBB` hex color or one of `NAMED_COLORS`, lowercased so each color is stored
+/// and filtered in one spelling
+fn parse_color(color: &str) -> Result<String, String> {
+    let color = color.trim().to_ascii_lowercase();
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex || NAMED_COLORS.contains(&color.as_str()) {
+        Ok(color)
+    } else {
+        Err(format!(
+            "Color must be a hex color such as #1e90ff or one of {}",
+            NAMED_COLORS.join(", ")
+        ))
+    }
+}
+
+fn validate_color(errors: &mut ValidationErrors, color: Option<&str>) -> Option<String> {
+    match color.map(parse_color).transpose() {
+        Ok(color) => color,
+        Err(err) => {
+            errors.add("color", err);
+            None
+        }
+    }
+}
+
+/// Reject a negative number of minutes; `name` is the field and `label` how messages call it
+fn validate_minutes(errors: &mut ValidationErrors, name: &str, label: &str, minutes: Option<i32>) {
+    if minutes.is_some_and(|m| m < 0) {
+        errors.add(name, format!("{} cannot be negative", label));
+    }
+}
+
+fn validate_time(errors: &mut ValidationErrors, estimated: Option<i32>, actual: Option<i32>) {
+    validate_minutes(errors, "estimated_minutes", "Estimated minutes", estimated);
+    validate_minutes(errors, "actual_minutes", "Actual minutes", actual);
+}
+
+fn validate_metadata(errors: &mut ValidationErrors, metadata: Option<&Value>) {
+    if metadata.is_some_and(|m| !m.is_object()) {
+        errors.add("metadata", "Metadata must be a JSON object");
+    }
+}
+
+/// Parse an optional priority string from a request body, recording a field
+/// error when it is not one of the allowed values
+fn validate_priority(errors: &mut ValidationErrors, priority: Option<&str>) -> Option<Priority> {
+    match priority.map(str::parse::<Priority>).transpose() {
+        Ok(priority) => priority,
+        Err(err) => {
+            errors.add("priority", err.to_string());
+            None
+        }
+    }
+}
+
+/// Parse an optional recurrence string from a request body, like `validate_priority`
+fn validate_recurrence(
+    errors: &mut ValidationErrors,
+    recurrence: Option<&str>,
+) -> Option<Recurrence> {
+    match recurrence.map(str::parse::<Recurrence>).transpose() {
+        Ok(recurrence) => recurrence,
+        Err(err) => {
+            errors.add("recurrence", err.to_string());
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct Todo {
     pub id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub recurrence: Option<Recurrence>,
+    /// Free-form JSON object the client stores alongside the todo, such as attachment URLs
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
+    /// `#RRGGBB` or one of the named colors, lowercase
+    pub color: Option<String>,
+    /// How long the todo is expected to take
+    pub estimated_minutes: Option<i32>,
+    /// How long it actually took, or has taken so far
+    pub actual_minutes: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub archived: bool,
+    /// Sort key for `sort_by=position`; only the relative order is meaningful
+    pub position: f64,
+    pub version: i32,
+    pub tags: Vec<String>,
+    pub parent_id: Option<Uuid>,
+    /// Number of live subtasks
+    pub child_count: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct TodoResponse {
     pub id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub recurrence: Option<Recurrence>,
+    /// Free-form JSON object the client stores alongside the todo, such as attachment URLs
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
+    /// `#RRGGBB` or one of the named colors, lowercase
+    pub color: Option<String>,
+    /// How long the todo is expected to take
+    pub estimated_minutes: Option<i32>,
+    /// How long it actually took, or has taken so far
+    pub actual_minutes: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub archived: bool,
+    /// Sort key for `sort_by=position`; only the relative order is meaningful
+    pub position: f64,
+    pub version: i32,
+    pub tags: Vec<String>,
+    pub parent_id: Option<Uuid>,
+    /// Number of live subtasks
+    pub child_count: i64,
+}
+
+/// Keys of a serialized `TodoResponse`, which `fields` may select from
+pub const TODO_FIELDS: &[&str] = &[
+    "id", "title", "description", "completed", "completed_at", "due_date", "priority",
+    "recurrence", "metadata", "color", "estimated_minutes", "actual_minutes", "created_at",
+    "updated_at", "deleted_at", "archived", "position", "version", "tags", "parent_id",
+    "child_count",
+];
+
+/// How each todo in a JSON list or get response is rendered
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutputParams {
+    /// Comma-separated todo keys to return, e.g. `id,title`; every key by default
+    pub fields: Option<String>,
+    /// IANA time zone, e.g. `Europe/Bucharest`, to give `created_at` and `updated_at` in;
+    /// UTC by default
+    pub tz: Option<String>,
+}
+
+impl OutputParams {
+    /// The requested keys, or `None` for the whole todo. Blank entries are ignored.
+    pub fn fields(&self) -> Result<Option<Vec<&'static str>>, ApiError> {
+        let Some(fields) = self.fields.as_deref() else {
+            return Ok(None);
+        };
+
+        let mut selected = Vec::new();
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let known = TODO_FIELDS.iter().find(|&&known| known == field).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Unknown field '{}'; expected any of {}",
+                    field,
+                    TODO_FIELDS.join(", ")
+                ))
+            })?;
+            selected.push(*known);
+        }
+
+        Ok(if selected.is_empty() { None } else { Some(selected) })
+    }
+
+    /// The zone to convert timestamps to, or `None` to leave them in UTC
+    pub fn tz(&self) -> Result<Option<Tz>, ApiError> {
+        self.tz
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>().map_err(|_| {
+                    ApiError::BadRequest(format!(
+                        "Unknown time zone '{}'; expected an IANA name such as Europe/London",
+                        name
+                    ))
+                })
+            })
+            .transpose()
+    }
 }
 
-#[derive(Debug, Deserialize)]
+/// Body of a create. `id` and the timestamps are always set by the server, so they and any
+/// other unknown key are rejected rather than silently dropped.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateTodoRequest {
     pub title: String,
     pub description: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// Make the new todo a subtask of this one
+    pub parent_id: Option<Uuid>,
+    #[schema(value_type = Option<Recurrence>)]
+    pub recurrence: Option<String>,
+    /// Any JSON object, stored and returned as is
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
+    /// `#RRGGBB`, or red, orange, yellow, green, blue, purple, pink, brown or gray
+    pub color: Option<String>,
+    /// Expected time in minutes, at least 0
+    pub estimated_minutes: Option<i32>,
+    /// Time already spent in minutes, at least 0
+    pub actual_minutes: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateTodoRequest {
+/// A create request that has passed validation and is ready to insert
+#[derive(Debug)]
+pub struct NewTodo {
+    pub title: String,
+    pub description: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub parent_id: Option<Uuid>,
+    pub recurrence: Option<Recurrence>,
+    pub metadata: Option<Value>,
+    pub color: Option<String>,
+    pub estimated_minutes: Option<i32>,
+    pub actual_minutes: Option<i32>,
+}
+
+impl CreateTodoRequest {
+    /// Check every field, reporting all problems at once
+    pub fn validate(&self) -> Result<NewTodo, ApiError> {
+        let mut errors = ValidationErrors::new();
+
+        let title = validate_title(&mut errors, &self.title);
+        validate_description(&mut errors, self.description.as_deref());
+        let priority = validate_priority(&mut errors, self.priority.as_deref());
+        let recurrence = validate_recurrence(&mut errors, self.recurrence.as_deref());
+        validate_metadata(&mut errors, self.metadata.as_ref());
+        let color = validate_color(&mut errors, self.color.as_deref());
+        validate_time(&mut errors, self.estimated_minutes, self.actual_minutes);
+        let tags = validate_tags(&mut errors, self.tags.as_deref().unwrap_or_default());
+
+        errors.into_result()?;
+
+        Ok(NewTodo {
+            title,
+            description: self.description.clone(),
+            due_date: self.due_date,
+            priority: priority.unwrap_or_default(),
+            tags,
+            parent_id: self.parent_id,
+            recurrence,
+            metadata: self.metadata.clone(),
+            color,
+            estimated_minutes: self.estimated_minutes,
+            actual_minutes: self.actual_minutes,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateParams {
+    /// Reject the create if an incomplete todo already has the same title, ignoring case
+    #[serde(default)]
+    pub dedupe: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicateParams {
+    /// Append ` (copy)` to the copy's title
+    #[serde(default)]
+    pub suffix: bool,
+}
+
+/// Added to a duplicate's title with `suffix=true`
+const COPY_SUFFIX: &str = " (copy)";
+
+impl Todo {
+    /// A new, pending todo with this one's content, tags, parent, recurrence, metadata, color
+    /// and estimate. No time has been spent on the copy yet.
+    pub fn duplicate(&self, suffix: bool) -> NewTodo {
+        let mut title = self.title.clone();
+        if suffix {
+            // Shorten the title rather than fail when the suffix would push it over the limit
+            let keep = MAX_TITLE_LENGTH - COPY_SUFFIX.chars().count();
+            if let Some((end, _)) = title.char_indices().nth(keep) {
+                title.truncate(end);
+            }
+            title.push_str(COPY_SUFFIX);
+        }
+        NewTodo {
+            title,
+            description: self.description.clone(),
+            due_date: self.due_date,
+            priority: self.priority,
+            tags: self.tags.clone(),
+            parent_id: self.parent_id,
+            recurrence: self.recurrence,
+            metadata: self.metadata.clone(),
+            color: self.color.clone(),
+            estimated_minutes: self.estimated_minutes,
+            actual_minutes: None,
+        }
+    }
+}
+
+/// One element of an import body: the shape `GET /api/todos` returns, where
+/// everything but `title` is optional and unknown fields are ignored
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportTodoRequest {
+    pub id: Option<Uuid>,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
+    pub color: Option<String>,
+    pub estimated_minutes: Option<i32>,
+    pub actual_minutes: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A validated import row. `id` and the timestamps are only kept when
+/// the import preserves IDs; otherwise the row is inserted as new.
+#[derive(Debug)]
+pub struct ImportedTodo {
+    pub id: Option<Uuid>,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub metadata: Option<Value>,
+    pub color: Option<String>,
+    pub estimated_minutes: Option<i32>,
+    pub actual_minutes: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ImportTodoRequest {
+    /// Check every field, reporting all problems at once
+    pub fn validate(&self) -> Result<ImportedTodo, ApiError> {
+        let mut errors = ValidationErrors::new();
+
+        let title = validate_title(&mut errors, &self.title);
+        validate_description(&mut errors, self.description.as_deref());
+        let priority = validate_priority(&mut errors, self.priority.as_deref());
+        validate_metadata(&mut errors, self.metadata.as_ref());
+        let color = validate_color(&mut errors, self.color.as_deref());
+        validate_time(&mut errors, self.estimated_minutes, self.actual_minutes);
+        let tags = validate_tags(&mut errors, self.tags.as_deref().unwrap_or_default());
+
+        errors.into_result()?;
+
+        Ok(ImportedTodo {
+            id: self.id,
+            title,
+            description: self.description.clone(),
+            completed: self.completed.unwrap_or_default(),
+            completed_at: self.completed_at,
+            due_date: self.due_date,
+            priority: priority.unwrap_or_default(),
+            tags,
+            metadata: self.metadata.clone(),
+            color,
+            estimated_minutes: self.estimated_minutes,
+            actual_minutes: self.actual_minutes,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// What an import does with a row whose ID is already taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    Skip,
+    Fail,
+}
+
+impl std::str::FromStr for OnConflict {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(OnConflict::Skip),
+            "fail" => Ok(OnConflict::Fail),
+            _ => Err(ApiError::BadRequest(format!(
+                "Unknown on_conflict '{}'; expected skip or fail",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// Keep each row's `id` and timestamps instead of assigning new ones
+    #[serde(default)]
+    pub preserve_ids: bool,
+    /// `fail` (default) rejects the whole import on an ID collision,
+    /// `skip` leaves the existing todo alone
+    pub on_conflict: Option<String>,
+}
+
+impl ImportParams {
+    pub fn on_conflict(&self) -> Result<OnConflict, ApiError> {
+        match self.on_conflict.as_deref() {
+            Some(on_conflict) => on_conflict.parse(),
+            None => Ok(OnConflict::Fail),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportResponse {
+    /// Number of todos inserted
+    pub imported: usize,
+    /// IDs left alone because a todo with that ID already existed (`on_conflict=skip`)
+    pub skipped: Vec<Uuid>,
+}
+
+/// Body of a PUT: replaces the whole todo, so `title` and `completed` are required,
+/// omitted nullable fields and `tags` are cleared and an omitted `priority` resets to medium
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaceTodoRequest {
     pub title: Option<String>,
     pub description: Option<String>,
     pub completed: Option<bool>,
+    pub due_date: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[schema(value_type = Option<Recurrence>)]
+    pub recurrence: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Value>,
+    pub color: Option<String>,
+    pub estimated_minutes: Option<i32>,
+    pub actual_minutes: Option<i32>,
+    /// When set, the update only applies if the todo is still at this version
+    pub version: Option<i32>,
+}
+
+/// The full set of values an update writes to a todo row
+#[derive(Debug)]
+pub struct TodoUpdate {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub due_date: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    pub recurrence: Option<Recurrence>,
+    pub metadata: Option<Value>,
+    pub color: Option<String>,
+    pub estimated_minutes: Option<i32>,
+    pub actual_minutes: Option<i32>,
+    /// Tags live in their own table; `None` leaves them untouched
+    pub tags: Option<Vec<String>>,
+    /// Optimistic lock: only write if the row is still at this version
+    pub expected_version: Option<i32>,
+}
+
+impl TodoUpdate {
+    /// Whether writing this would change any of `todo`'s values
+    pub fn changes(&self, todo: &Todo) -> bool {
+        self.completed != todo.completed || self.changes_details(todo)
+    }
+
+    /// Whether writing this would change anything of `todo`'s besides `completed`
+    pub fn changes_details(&self, todo: &Todo) -> bool {
+        // Validation leaves the requested tags sorted already
+        let tags_change = self.tags.as_ref().is_some_and(|tags| {
+            let mut current = todo.tags.clone();
+            current.sort();
+            *tags != current
+        });
+        tags_change
+            || self.title != todo.title
+            || self.description != todo.description
+            || self.due_date != todo.due_date
+            || self.priority != todo.priority
+            || self.recurrence != todo.recurrence
+            || self.metadata != todo.metadata
+            || self.color != todo.color
+            || self.estimated_minutes != todo.estimated_minutes
+            || self.actual_minutes != todo.actual_minutes
+    }
+}
+
+impl ReplaceTodoRequest {
+    /// Check every field, reporting all problems at once
+    pub fn validate(&self) -> Result<TodoUpdate, ApiError> {
+        let mut errors = ValidationErrors::new();
+
+        let title = match self.title.as_deref() {
+            Some(title) => validate_title(&mut errors, title),
+            None => {
+                errors.add("title", "Title is required");
+                String::new()
+            }
+        };
+        validate_description(&mut errors, self.description.as_deref());
+        if self.completed.is_none() {
+            errors.add("completed", "Completed is required");
+        }
+        let priority = validate_priority(&mut errors, self.priority.as_deref());
+        let recurrence = validate_recurrence(&mut errors, self.recurrence.as_deref());
+        validate_metadata(&mut errors, self.metadata.as_ref());
+        let color = validate_color(&mut errors, self.color.as_deref());
+        validate_time(&mut errors, self.estimated_minutes, self.actual_minutes);
+        let tags = validate_tags(&mut errors, self.tags.as_deref().unwrap_or_default());
+
+        errors.into_result()?;
+
+        Ok(TodoUpdate {
+            title,
+            description: self.description.clone(),
+            completed: self.completed.unwrap_or_default(),
+            due_date: self.due_date,
+            priority: priority.unwrap_or_default(),
+            recurrence,
+            metadata: self.metadata.clone(),
+            color,
+            estimated_minutes: self.estimated_minutes,
+            actual_minutes: self.actual_minutes,
+            tags: Some(tags),
+            expected_version: self.version,
+        })
+    }
+}
+
+/// Body of a PATCH: omitted fields keep their current value.
+/// Nullable fields are `Some(None)` when the client sent an explicit `null` to clear them.
+/// Unknown keys are rejected, so a misspelled field can't pass as an empty update.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateTodoRequest {
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>, nullable)]
+    pub description: Option<Option<String>>,
+    pub completed: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<DateTime<Utc>>, nullable)]
+    pub due_date: Option<Option<DateTime<Utc>>>,
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Recurrence>, nullable)]
+    pub recurrence: Option<Option<String>>,
+    /// Replaces the whole object, or with a merge patch is merged into it key by key
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Object>, nullable)]
+    pub metadata: Option<Option<Value>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(nullable)]
+    pub color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(nullable)]
+    pub estimated_minutes: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(nullable)]
+    pub actual_minutes: Option<Option<i32>>,
+    /// When set, the update only applies if the todo is still at this version
+    pub version: Option<i32>,
+    /// Set by `from_merge_patch`, never read from the body
+    #[serde(skip)]
+    merge: bool,
+}
+
+/// Keys of `TODO_FIELDS` a PATCH body may set; the server maintains the rest
+const PATCHABLE_FIELDS: &[&str] = &[
+    "title", "description", "completed", "due_date", "priority", "tags", "recurrence", "metadata",
+    "color", "estimated_minutes", "actual_minutes", "version",
+];
+
+impl UpdateTodoRequest {
+    /// Read an `application/json` PATCH body
+    pub fn from_json(body: Value) -> Result<Self, ApiError> {
+        serde_json::from_value(body)
+            .map_err(|err| ApiError::BadRequest(format!("Invalid request body: {}", err)))
+    }
+
+    /// Read an `application/merge-patch+json` body (RFC 7386): a `null` removes the value,
+    /// so `"tags": null` clears every tag, and `metadata` is merged into the current object.
+    /// Fields that cannot be empty reject `null`, and read-only fields such as `id` or
+    /// `created_at` reject any value.
+    pub fn from_merge_patch(mut body: Value) -> Result<Self, ApiError> {
+        let Value::Object(patch) = &mut body else {
+            return Err(ApiError::BadRequest(
+                "Invalid merge patch: expected a JSON object".to_string(),
+            ));
+        };
+
+        let mut errors = ValidationErrors::new();
+        for (key, value) in patch.iter_mut() {
+            let key = key.as_str();
+            if TODO_FIELDS.contains(&key) && !PATCHABLE_FIELDS.contains(&key) {
+                errors.add(key, format!("{} cannot be changed", key));
+            } else if value.is_null() {
+                match key {
+                    "tags" => *value = Value::Array(Vec::new()),
+                    "title" | "completed" | "priority" | "version" => {
+                        errors.add(key, format!("{} cannot be removed", key))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        errors.into_result()?;
+
+        Ok(Self { merge: true, ..Self::from_json(body)? })
+    }
+}
+
+/// A validated PATCH; `None` fields keep the todo's current value
+#[derive(Debug)]
+pub struct TodoPatch {
+    pub title: Option<String>,
+    pub description: Option<Option<String>>,
+    pub completed: Option<bool>,
+    pub due_date: Option<Option<DateTime<Utc>>>,
+    pub priority: Option<Priority>,
+    pub tags: Option<Vec<String>>,
+    pub recurrence: Option<Option<Recurrence>>,
+    pub metadata: Option<Option<Value>>,
+    pub color: Option<Option<String>>,
+    pub estimated_minutes: Option<Option<i32>>,
+    pub actual_minutes: Option<Option<i32>>,
+    /// Merge `metadata` into the current object rather than replace it
+    pub merge_metadata: bool,
+    pub expected_version: Option<i32>,
+}
+
+impl UpdateTodoRequest {
+    /// Check every field, reporting all problems at once
+    pub fn validate(&self) -> Result<TodoPatch, ApiError> {
+        let mut errors = ValidationErrors::new();
+
+        let title = self.title.as_deref().map(|title| validate_title(&mut errors, title));
+        validate_description(&mut errors, self.description.as_ref().and_then(|d| d.as_deref()));
+        let priority = validate_priority(&mut errors, self.priority.as_deref());
+        let recurrence = self
+            .recurrence
+            .as_ref()
+            .map(|r| validate_recurrence(&mut errors, r.as_deref()));
+        validate_metadata(&mut errors, self.metadata.as_ref().and_then(Option::as_ref));
+        let color = self.color.as_ref().map(|c| validate_color(&mut errors, c.as_deref()));
+        validate_time(
+            &mut errors,
+            self.estimated_minutes.flatten(),
+            self.actual_minutes.flatten(),
+        );
+        let tags = self.tags.as_ref().map(|tags| validate_tags(&mut errors, tags));
+
+        errors.into_result()?;
+
+        Ok(TodoPatch {
+            title,
+            description: self.description.clone(),
+            completed: self.completed,
+            due_date: self.due_date,
+            priority,
+            tags,
+            recurrence,
+            metadata: self.metadata.clone(),
+            color,
+            estimated_minutes: self.estimated_minutes,
+            actual_minutes: self.actual_minutes,
+            merge_metadata: self.merge,
+            expected_version: self.version,
+        })
+    }
+}
+
+impl TodoPatch {
+    /// Merge the patch onto the current row
+    pub fn apply(self, existing: &Todo) -> TodoUpdate {
+        TodoUpdate {
+            title: self.title.unwrap_or_else(|| existing.title.clone()),
+            description: self.description.unwrap_or_else(|| existing.description.clone()),
+            completed: self.completed.unwrap_or(existing.completed),
+            due_date: self.due_date.unwrap_or(existing.due_date),
+            priority: self.priority.unwrap_or(existing.priority),
+            recurrence: self.recurrence.unwrap_or(existing.recurrence),
+            metadata: match self.metadata {
+                Some(Some(patch)) if self.merge_metadata => {
+                    Some(merge_patch(existing.metadata.clone(), patch))
+                }
+                Some(metadata) => metadata,
+                None => existing.metadata.clone(),
+            },
+            color: self.color.unwrap_or_else(|| existing.color.clone()),
+            estimated_minutes: self.estimated_minutes.unwrap_or(existing.estimated_minutes),
+            actual_minutes: self.actual_minutes.unwrap_or(existing.actual_minutes),
+            tags: self.tags,
+            expected_version: self.expected_version,
+        }
+    }
+}
+
+/// Apply an RFC 7386 merge patch to `target`: objects merge key by key, a `null` removes
+/// the key and anything else replaces the value
+fn merge_patch(target: Option<Value>, patch: Value) -> Value {
+    let Value::Object(patch) = patch else {
+        return patch;
+    };
+    let mut merged = match target {
+        Some(Value::Object(target)) => target,
+        _ => serde_json::Map::new(),
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            merged.remove(&key);
+        } else {
+            let current = merged.remove(&key);
+            merged.insert(key, merge_patch(current, value));
+        }
+    }
+    Value::Object(merged)
+}
+
+/// Deserialize a present key (even `null`) as `Some`, so that together with
+/// `#[serde(default)]` an absent key stays `None`
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MoveTodoRequest {
+    /// Where the todo should end up in the `sort_by=position&order=asc` list, counting from 0.
+    /// Past the end moves it to the end.
+    pub index: i64,
+}
+
+impl MoveTodoRequest {
+    pub fn validate(&self) -> Result<i64, ApiError> {
+        let mut errors = ValidationErrors::new();
+        if self.index < 0 {
+            errors.add("index", "Index cannot be negative");
+        }
+        errors.into_result()?;
+        Ok(self.index)
+    }
+}
+
+/// Where `PUT /api/todos/order` puts the todos its body does not list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Unlisted {
+    /// They keep their places, and the listed todos are reordered among the slots they held
+    #[default]
+    Keep,
+    /// They follow the listed todos, in their current order
+    End,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReorderTodosRequest {
+    /// Todos in the order they should appear in the `sort_by=position&order=asc` list
+    pub ids: Vec<Uuid>,
+    #[serde(default)]
+    pub unlisted: Unlisted,
+}
+
+impl ReorderTodosRequest {
+    /// Reject an empty list and IDs given more than once, which have no single place
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.ids.is_empty() {
+            return Err(ApiError::BadRequest("ids cannot be empty".to_string()));
+        }
+        let mut errors = ValidationErrors::new();
+        let mut seen = HashSet::new();
+        for (index, id) in self.ids.iter().enumerate() {
+            if !seen.insert(id) {
+                errors.add(format!("ids[{}]", index), format!("{} is listed more than once", id));
+            }
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteParams {
+    /// Remove the row permanently instead of setting `deleted_at`
+    #[serde(default)]
+    pub hard: bool,
+    /// Also delete the todo's subtasks; without it, a todo with subtasks is not deleted
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DryRunParams {
+    /// Report which todos would change without changing them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchDeleteRequest {
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchDeleteResponse {
+    /// Number of distinct IDs in the request
+    pub requested: usize,
+    /// Number of rows actually removed (or that would be, on a dry run); lower than
+    /// `requested` when some IDs did not exist, and higher when `cascade` took subtasks too
+    pub deleted: u64,
+    /// Present and `true` only on a `?dry_run=true` preview, when nothing was deleted
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// On a dry run, the todos that would be deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todos: Option<Vec<TodoResponse>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PurgeCompletedResponse {
+    /// Number of completed todos removed (or that would be, on a dry run)
+    pub deleted: u64,
+    /// Present and `true` only on a `?dry_run=true` preview, when nothing was deleted
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// On a dry run, the todos that would be deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todos: Option<Vec<TodoResponse>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompleteAllResponse {
+    /// Number of todos whose `completed` flag changed (or would change, on a dry run); ones
+    /// already in that state are skipped
+    pub updated: u64,
+    /// Present and `true` only on a `?dry_run=true` preview, when nothing was updated
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// On a dry run, the todos that would change, as they are now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todos: Option<Vec<TodoResponse>>,
+}
+
+/// One page of pending and one of completed todos, each in the requested order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TodoBoard {
+    pub pending: Vec<TodoResponse>,
+    pub completed: Vec<TodoResponse>,
+}
+
+/// Just enough of a todo for a sync client to tell whether its copy is current
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TodoStamp {
+    pub id: Uuid,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Titles `GET /api/todos/autocomplete` returns by default, and at most
+const DEFAULT_SUGGESTIONS: i64 = 10;
+const MAX_SUGGESTIONS: i64 = 50;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AutocompleteParams {
+    /// Start of the title, matched ignoring case
+    pub q: Option<String>,
+    /// Most titles to return (default 10, capped at 50)
+    pub limit: Option<i64>,
+}
+
+impl AutocompleteParams {
+    /// The prefix, without leading whitespace since titles never start with any, and the
+    /// effective limit
+    pub fn resolve(&self) -> Result<(&str, i64), ApiError> {
+        let prefix = self.q.as_deref().map(str::trim_start).unwrap_or_default();
+        if prefix.is_empty() {
+            return Err(ApiError::BadRequest("q must not be blank".to_string()));
+        }
+        let limit = self.limit.unwrap_or(DEFAULT_SUGGESTIONS);
+        if limit <= 0 {
+            return Err(ApiError::BadRequest("Limit must be greater than 0".to_string()));
+        }
+        Ok((prefix, limit.min(MAX_SUGGESTIONS)))
+    }
+}
+
+/// A todo offered while its title is being typed
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TodoSuggestion {
+    pub id: Uuid,
+    pub title: String,
+}
+
+/// Aggregate counts over the caller's live todos
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TodoStats {
+    pub total: i64,
+    pub completed: i64,
+    pub pending: i64,
+    /// Pending todos whose due date has passed
+    pub overdue: i64,
+    /// Sum of `estimated_minutes` over completed todos
+    pub completed_estimated_minutes: i64,
+    /// Sum of `actual_minutes` over completed todos
+    pub completed_actual_minutes: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TodoFilter {
+    /// Only completed (`true`) or pending (`false`) todos
+    pub completed: Option<bool>,
+    /// Case-insensitive match against title and description
+    pub search: Option<String>,
+    /// Only todos carrying this tag
+    pub tag: Option<String>,
+    /// Only todos of this color, `#RRGGBB` or a named color
+    pub color: Option<String>,
+    /// Also return archived todos, which are hidden by default
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Only todos created at or after this RFC 3339 timestamp
+    pub created_after: Option<String>,
+    /// Only todos created at or before this RFC 3339 timestamp
+    pub created_before: Option<String>,
+    /// Only todos updated after this RFC 3339 timestamp, archived and deleted ones included;
+    /// pass the previous response's `X-Sync-Watermark`
+    pub changed_since: Option<String>,
+    /// Only these todos, as comma-separated UUIDs (at most 100); unknown ones are left out
+    pub ids: Option<String>,
+}
+
+impl TodoFilter {
+    /// The search term, or `None` when it is missing or only whitespace
+    pub fn search_term(&self) -> Option<&str> {
+        self.search.as_deref().map(str::trim).filter(|s| !s.is_empty())
+    }
+
+    /// Parse `created_after` and `created_before`, rejecting malformed or inverted bounds
+    pub fn created_range(&self) -> Result<TimeRange, ApiError> {
+        let after = parse_timestamp("created_after", self.created_after.as_deref())?;
+        let before = parse_timestamp("created_before", self.created_before.as_deref())?;
+        if let (Some(after), Some(before)) = (after, before) {
+            if after > before {
+                return Err(ApiError::BadRequest(
+                    "created_after must not be later than created_before".to_string(),
+                ));
+            }
+        }
+        Ok(TimeRange { after, before })
+    }
+
+    /// The tag to filter by in its stored spelling
+    pub fn tag(&self) -> Option<String> {
+        self.tag.as_deref().map(normalize_tag)
+    }
+
+    /// The color to filter by in its stored spelling, rejecting values no todo can have
+    pub fn color(&self) -> Result<Option<String>, ApiError> {
+        self.color.as_deref().map(parse_color).transpose().map_err(ApiError::BadRequest)
+    }
+
+    /// Parse `changed_since`, rejecting a malformed timestamp and the filters a changed todo
+    /// could stop matching, since the sync would then never report the change
+    pub fn changed_since(&self) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let since = parse_timestamp("changed_since", self.changed_since.as_deref())?;
+        let filtered = self.completed.is_some()
+            || self.search_term().is_some()
+            || self.tag.is_some()
+            || self.color.is_some()
+            || self.created_after.is_some()
+            || self.created_before.is_some();
+        if since.is_some() && filtered {
+            return Err(ApiError::BadRequest(
+                "changed_since cannot be combined with completed, search, tag, color, \
+                 created_after or created_before"
+                    .to_string(),
+            ));
+        }
+        Ok(since)
+    }
+
+    /// Parse `ids`, rejecting anything that is not a UUID and more IDs than fit in a page
+    pub fn ids(&self) -> Result<Option<Vec<Uuid>>, ApiError> {
+        let Some(ids) = self.ids.as_deref() else {
+            return Ok(None);
+        };
+        let ids = ids
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                Uuid::parse_str(id).map_err(|_| {
+                    ApiError::BadRequest(format!(
+                        "ids must be comma-separated UUIDs, got '{}'",
+                        id
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if ids.len() as i64 > MAX_PAGE_LIMIT {
+            return Err(ApiError::BadRequest(format!(
+                "ids accepts at most {} IDs, got {}",
+                MAX_PAGE_LIMIT,
+                ids.len()
+            )));
+        }
+        Ok(Some(ids))
+    }
+}
+
+/// Inclusive bounds on a timestamp column; either end may be open
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeRange {
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+fn parse_timestamp(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, ApiError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|timestamp| Some(timestamp.with_timezone(&Utc)))
+        .map_err(|_| {
+            ApiError::BadRequest(format!(
+                "{} must be an RFC 3339 timestamp such as 2024-01-15T10:30:00Z, got '{}'",
+                name, value
+            ))
+        })
+}
+
+/// Columns `list_todos` may be ordered by. Column names cannot be bound as
+/// query parameters, so only these fixed identifiers ever reach the SQL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    CreatedAt,
+    UpdatedAt,
+    Title,
+    Completed,
+    DueDate,
+    Priority,
+    Position,
+}
+
+impl SortField {
+    pub fn column(self) -> &'static str {
+        match self {
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+            SortField::Title => "title",
+            SortField::Completed => "completed",
+            SortField::DueDate => "due_date",
+            SortField::Priority => "priority",
+            SortField::Position => "position",
+        }
+    }
+}
+
+impl std::str::FromStr for SortField {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created_at" => Ok(SortField::CreatedAt),
+            "updated_at" => Ok(SortField::UpdatedAt),
+            "title" => Ok(SortField::Title),
+            "completed" => Ok(SortField::Completed),
+            "due_date" => Ok(SortField::DueDate),
+            "priority" => Ok(SortField::Priority),
+            "position" => Ok(SortField::Position),
+            _ => Err(ApiError::BadRequest(format!(
+                "Unknown sort field '{}'; expected one of created_at, updated_at, title, completed, due_date, priority, position",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(ApiError::BadRequest(format!(
+                "Unknown sort order '{}'; expected asc or desc",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SortParams {
+    /// One of `created_at` (default), `updated_at`, `title`, `completed`, `due_date`, `priority`,
+    /// `position`
+    pub sort_by: Option<String>,
+    /// `asc` or `desc` (default)
+    pub order: Option<String>,
+    /// List every pending todo before the completed ones, each group in the order above
+    #[serde(default)]
+    pub pending_first: bool,
+}
+
+impl SortParams {
+    /// Validate the requested ordering, defaulting to newest first
+    pub fn resolve(&self) -> Result<(SortField, SortOrder), ApiError> {
+        let field = match self.sort_by.as_deref() {
+            Some(sort_by) => sort_by.parse()?,
+            None => SortField::CreatedAt,
+        };
+        let order = match self.order.as_deref() {
+            Some(order) => order.parse()?,
+            None => SortOrder::Desc,
+        };
+
+        Ok((field, order))
+    }
 }
 
 impl From<Todo> for TodoResponse {
@@ -42,8 +1312,23 @@ impl From<Todo> for TodoResponse {
             title: todo.title,
             description: todo.description,
             completed: todo.completed,
+            completed_at: todo.completed_at,
+            due_date: todo.due_date,
+            priority: todo.priority,
+            recurrence: todo.recurrence,
+            metadata: todo.metadata,
+            color: todo.color,
+            estimated_minutes: todo.estimated_minutes,
+            actual_minutes: todo.actual_minutes,
             created_at: todo.created_at,
             updated_at: todo.updated_at,
+            deleted_at: todo.deleted_at,
+            archived: todo.archived,
+            position: todo.position,
+            version: todo.version,
+            tags: todo.tags,
+            parent_id: todo.parent_id,
+            child_count: todo.child_count,
         }
     }
 }
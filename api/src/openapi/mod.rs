@@ -0,0 +1,101 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::error::{ErrorResponse, FieldError};
+use crate::handlers;
+use crate::models::{
+    BatchDeleteRequest, BatchDeleteResponse, CompleteAllResponse, CreateTodoRequest, FieldChange,
+    HealthResponse, ImportResponse, ImportTodoRequest, MoveTodoRequest, Priority,
+    PurgeCompletedResponse, Recurrence, ReorderTodosRequest, ReplaceTodoRequest, Todo, TodoBoard,
+    TodoHistoryEntry, TodoResponse, TodoStamp, TodoStats, TodoSuggestion, Unlisted,
+    UpdateTodoRequest,
+};
+
+/// OpenAPI document assembled from the `#[utoipa::path]` annotations on the handlers
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Todo API", description = "CRUD API for todos"),
+    paths(
+        handlers::health::health_check,
+        handlers::metrics::metrics,
+        handlers::todo::list_todos,
+        handlers::todo::todo_stats,
+        handlers::todo::todo_board,
+        handlers::todo::list_todo_ids,
+        handlers::todo::list_trash,
+        handlers::todo::autocomplete_todos,
+        handlers::events::todo_events,
+        handlers::events::todo_stream,
+        handlers::export::export_csv,
+        handlers::todo::get_todo,
+        handlers::todo::create_todo,
+        handlers::todo::batch_create_todos,
+        handlers::todo::import_todos,
+        handlers::todo::update_todo,
+        handlers::todo::patch_todo,
+        handlers::todo::delete_todo,
+        handlers::todo::list_subtasks,
+        handlers::todo::duplicate_todo,
+        handlers::todo::restore_todo,
+        handlers::todo::toggle_todo,
+        handlers::todo::move_todo,
+        handlers::todo::reorder_todos,
+        handlers::todo::todo_history,
+        handlers::todo::archive_todo,
+        handlers::todo::unarchive_todo,
+        handlers::todo::batch_delete_todos,
+        handlers::todo::purge_completed_todos,
+        handlers::todo::complete_all_todos,
+        handlers::todo::incomplete_all_todos,
+    ),
+    components(schemas(
+        Todo,
+        TodoResponse,
+        TodoStats,
+        TodoBoard,
+        TodoStamp,
+        TodoSuggestion,
+        TodoHistoryEntry,
+        FieldChange,
+        CreateTodoRequest,
+        ReplaceTodoRequest,
+        UpdateTodoRequest,
+        MoveTodoRequest,
+        ReorderTodosRequest,
+        Unlisted,
+        BatchDeleteRequest,
+        BatchDeleteResponse,
+        PurgeCompletedResponse,
+        CompleteAllResponse,
+        ImportTodoRequest,
+        ImportResponse,
+        Priority,
+        Recurrence,
+        HealthResponse,
+        ErrorResponse,
+        FieldError,
+    )),
+    tags(
+        (name = "todos", description = "Todo management"),
+        (name = "health", description = "Service health"),
+    ),
+    modifiers(&SecuritySchemes)
+)]
+pub struct ApiDoc;
+
+/// Registers the two ways of presenting `API_KEY` so Swagger UI can send it
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
@@ -1,13 +1,123 @@
-use actix_web::web;
+use actix_web::middleware::from_fn;
+use actix_web::{web, Route};
+use std::env;
+use utoipa::openapi::Server;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
 use crate::handlers;
+use crate::middleware::{authenticate, request_timeout, require_api_key};
+use crate::openapi::ApiDoc;
+
+/// Apply the request timeout to one route; see `middleware::request_timeout` for why it
+/// cannot wrap the app as a whole
+fn timed(route: Route) -> Route {
+    route.wrap(from_fn(request_timeout))
+}
+
+/// Prefix put in front of every route, from `API_BASE_PATH`, for running behind a proxy that
+/// forwards a sub-path such as `/todo-service` without rewriting it. Empty by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiBasePath(String);
+
+impl ApiBasePath {
+    /// Check a prefix; a trailing slash is dropped, so `/` is the same as no prefix
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let path = path.trim_end_matches('/');
+        if !path.is_empty() && !path.starts_with('/') || path.contains(['{', '}', '?', '#']) {
+            return Err(format!(
+                "API_BASE_PATH must be a path starting with /, got '{}'",
+                path
+            ));
+        }
+        Ok(Self(path.to_string()))
+    }
+
+    /// Read `API_BASE_PATH`; `Config::from_env` has already rejected a malformed one
+    pub fn from_env() -> Self {
+        env::var("API_BASE_PATH")
+            .ok()
+            .and_then(|path| Self::parse(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// `path` under the prefix
+    pub fn join(&self, path: &str) -> String {
+        format!("{}{}", self.0, path)
+    }
 
+    /// What is left of a request path once the prefix is removed, `None` outside it
+    pub fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        path.strip_prefix(self.0.as_str())
+    }
+}
+
+/// Register every route under the `API_BASE_PATH` prefix
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/api/todos")
-            .route("", web::get().to(handlers::list_todos))
-            .route("", web::post().to(handlers::create_todo))
-            .route("/{id}", web::get().to(handlers::get_todo))
-            .route("/{id}", web::put().to(handlers::update_todo))
-            .route("/{id}", web::delete().to(handlers::delete_todo))
-    );
+    configure_routes_at(ApiBasePath::from_env())(cfg)
+}
+
+/// Register every route under `base`, e.g. `/todo-service/api/todos` for `/todo-service`
+pub fn configure_routes_at(base: ApiBasePath) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        // Also answers unmatched paths inside scopes such as /api/todos
+        cfg.default_service(web::to(handlers::route_not_found));
+
+        cfg.route(&base.join("/health"), timed(web::get().to(handlers::health_check)));
+        cfg.route(&base.join("/metrics"), timed(web::get().to(handlers::metrics)));
+
+        // The documented paths stay as they are; the server entry tells Swagger UI where
+        // they actually live so "Try it out" reaches them
+        let mut doc = ApiDoc::openapi();
+        if !base.as_str().is_empty() {
+            doc.servers = Some(vec![Server::new(base.as_str())]);
+        }
+        cfg.service(
+            SwaggerUi::new(base.join("/swagger-ui/{_:.*}"))
+                .url(base.join("/api-docs/openapi.json"), doc),
+        );
+
+        cfg.service(
+            // Everything under /api/todos needs the API key and a user token when those are
+            // configured; /health and the docs stay open
+            web::scope(&base.join("/api/todos"))
+                .wrap(from_fn(authenticate))
+                .wrap(from_fn(require_api_key))
+                .route("", timed(web::get().to(handlers::list_todos)))
+                .route("", timed(web::post().to(handlers::create_todo)))
+                .route("/stats", timed(web::get().to(handlers::todo_stats)))
+                .route("/board", timed(web::get().to(handlers::todo_board)))
+                .route("/ids", timed(web::get().to(handlers::list_todo_ids)))
+                .route("/trash", timed(web::get().to(handlers::list_trash)))
+                .route("/autocomplete", timed(web::get().to(handlers::autocomplete_todos)))
+                .route("/events", timed(web::get().to(handlers::todo_events)))
+                .route("/stream", timed(web::get().to(handlers::todo_stream)))
+                .route("/export.csv", timed(web::get().to(handlers::export_csv)))
+                .route("/batch", timed(web::post().to(handlers::batch_create_todos)))
+                .route("/import", timed(web::post().to(handlers::import_todos)))
+                .route("/batch-delete", timed(web::post().to(handlers::batch_delete_todos)))
+                .route("/completed", timed(web::delete().to(handlers::purge_completed_todos)))
+                .route("/complete-all", timed(web::post().to(handlers::complete_all_todos)))
+                .route("/incomplete-all", timed(web::post().to(handlers::incomplete_all_todos)))
+                .route("/order", timed(web::put().to(handlers::reorder_todos)))
+                .route("/{id}", timed(web::get().to(handlers::get_todo)))
+                .route("/{id}", timed(web::put().to(handlers::update_todo)))
+                .route("/{id}", timed(web::patch().to(handlers::patch_todo)))
+                .route("/{id}", timed(web::delete().to(handlers::delete_todo)))
+                .route("/{id}/toggle", timed(web::post().to(handlers::toggle_todo)))
+                .route("/{id}/position", timed(web::put().to(handlers::move_todo)))
+                .route("/{id}/subtasks", timed(web::get().to(handlers::list_subtasks)))
+                .route("/{id}/history", timed(web::get().to(handlers::todo_history)))
+                .route("/{id}/duplicate", timed(web::post().to(handlers::duplicate_todo)))
+                .route("/{id}/restore", timed(web::post().to(handlers::restore_todo)))
+                .route("/{id}/archive", timed(web::post().to(handlers::archive_todo)))
+                .route("/{id}/unarchive", timed(web::post().to(handlers::unarchive_todo)))
+        );
+        // Rate limiting and `Location` headers need the prefix too
+        cfg.app_data(web::Data::new(base));
+    }
 }
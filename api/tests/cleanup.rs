@@ -0,0 +1,97 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use common::{TestContext, TodoFixture};
+use todo_app::db::{Cleanup, CleanupReport};
+
+async fn soft_delete(ctx: &TestContext, id: Uuid, days_ago: i64) {
+    sqlx::query("UPDATE todos SET deleted_at = $1 WHERE id = $2")
+        .bind(Utc::now() - Duration::days(days_ago))
+        .bind(id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn cleanup_removes_expired_rows_once_at_a_time() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let old = TodoFixture::new("Deleted long ago").insert(&ctx.pool).await;
+    soft_delete(&ctx, old, 40).await;
+    let recent = TodoFixture::new("Deleted yesterday").insert(&ctx.pool).await;
+    soft_delete(&ctx, recent, 1).await;
+    let kept = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    for (key, age) in [("expired", Duration::days(2)), ("fresh", Duration::minutes(5))] {
+        sqlx::query("INSERT INTO idempotency_keys (key, todo_id, created_at) VALUES ($1, $2, $3)")
+            .bind(key)
+            .bind(kept)
+            .bind(Utc::now() - age)
+            .execute(&ctx.pool)
+            .await
+            .unwrap();
+    }
+    let cleanup = Cleanup::new(
+        std::time::Duration::from_secs(60),
+        Duration::days(1),
+        Some(Duration::days(30)),
+    );
+
+    // Another instance in the middle of a pass holds the lock
+    let mut other = ctx.pool.begin().await.unwrap();
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('todo_app.cleanup', 0))")
+        .execute(&mut *other)
+        .await
+        .unwrap();
+    assert_eq!(cleanup.run_once(&ctx.pool).await.unwrap(), None);
+    other.commit().await.unwrap();
+
+    let report = cleanup.run_once(&ctx.pool).await.unwrap();
+    assert_eq!(report, Some(CleanupReport { idempotency_keys: 1, todos: 1 }));
+    let remaining: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM todos ORDER BY created_at")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert!(!remaining.contains(&old));
+    assert!(remaining.contains(&recent) && remaining.contains(&kept));
+    let keys: Vec<String> = sqlx::query_scalar("SELECT key FROM idempotency_keys")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(keys, ["fresh"]);
+}
+
+#[actix_web::test]
+async fn cleanup_waits_for_recently_deleted_subtasks() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let parent = TodoFixture::new("Deleted long ago").insert(&ctx.pool).await;
+    let child = TodoFixture::new("Deleted yesterday").insert(&ctx.pool).await;
+    sqlx::query("UPDATE todos SET parent_id = $1 WHERE id = $2")
+        .bind(parent)
+        .bind(child)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    soft_delete(&ctx, parent, 40).await;
+    soft_delete(&ctx, child, 1).await;
+    let cleanup = Cleanup::new(
+        std::time::Duration::from_secs(60),
+        Duration::days(1),
+        Some(Duration::days(30)),
+    );
+
+    // Purging the parent would cascade to the subtask, which can still be restored
+    let report = cleanup.run_once(&ctx.pool).await.unwrap();
+    assert_eq!(report, Some(CleanupReport { idempotency_keys: 0, todos: 0 }));
+
+    // Once the subtask has been in the trash as long, both go
+    soft_delete(&ctx, child, 31).await;
+    let report = cleanup.run_once(&ctx.pool).await.unwrap();
+    assert_eq!(report, Some(CleanupReport { idempotency_keys: 0, todos: 2 }));
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
@@ -0,0 +1,192 @@
+// Each test binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tokio::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+use todo_app::{db, error};
+use todo_app::events::EventBus;
+use todo_app::routes::{self, ApiBasePath};
+
+/// Tests share one database, so they take turns: every test holds this for its whole run
+static DATABASE_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// A clean, migrated test database and the app data handlers need.
+///
+/// Handlers open their own transactions from the pool, so a test cannot be wrapped in a
+/// single rolled-back transaction. Instead each test gets the database to itself and starts
+/// from empty tables.
+pub struct TestContext {
+    pub pool: PgPool,
+    pub bus: web::Data<EventBus>,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestContext {
+    /// Connect to `TEST_DATABASE_URL`, apply the migrations and empty every table.
+    /// Returns `None` when the variable is unset so `cargo test` passes without a database.
+    pub async fn new() -> Option<Self> {
+        let Ok(database_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL is not set; skipping database test");
+            return None;
+        };
+
+        let guard = DATABASE_LOCK.lock().await;
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .expect("Failed to connect to TEST_DATABASE_URL");
+        db::run_migrations(&pool)
+            .await
+            .expect("Failed to run migrations");
+        // todo_tags rows go with their todos
+        sqlx::query("TRUNCATE todos CASCADE")
+            .execute(&pool)
+            .await
+            .expect("Failed to truncate tables");
+
+        Some(TestContext {
+            pool,
+            bus: web::Data::new(EventBus::new()),
+            _guard: guard,
+        })
+    }
+
+    /// The app as `main` builds it, without the outer logging, CORS and rate-limit middleware.
+    /// Add more `app_data` (an `ApiKey`, a `JwtAuth`) before passing it to `test::init_service`.
+    pub fn app(
+        &self,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        self.app_at(ApiBasePath::default())
+    }
+
+    /// `app` with every route under `base`
+    pub fn app_at(
+        &self,
+        base: ApiBasePath,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        App::new()
+            .app_data(web::Data::new(self.pool.clone()))
+            .app_data(self.bus.clone())
+            .app_data(error::json_config())
+            .app_data(web::PathConfig::default().error_handler(error::path_error_handler))
+            .configure(routes::configure_routes_at(base))
+    }
+}
+
+/// Split a response into its status and JSON body; an empty body reads as `null`
+pub async fn read_json<B: MessageBody>(resp: ServiceResponse<B>) -> (StatusCode, Value) {
+    let status = resp.status();
+    let body = test::read_body(resp).await;
+    let json = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body).expect("Response body is not JSON")
+    };
+    (status, json)
+}
+
+/// Builds a todo row directly in the database, for setting up state the API
+/// cannot produce (fixed timestamps, other owners)
+pub struct TodoFixture {
+    title: String,
+    description: Option<String>,
+    completed: bool,
+    created_at: DateTime<Utc>,
+    tags: Vec<String>,
+    user_id: Option<Uuid>,
+}
+
+impl TodoFixture {
+    pub fn new(title: &str) -> Self {
+        TodoFixture {
+            title: title.to_string(),
+            description: None,
+            completed: false,
+            created_at: Utc::now(),
+            tags: Vec::new(),
+            user_id: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn completed(mut self) -> Self {
+        self.completed = true;
+        self
+    }
+
+    /// Sets both `created_at` and `updated_at`
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = created_at;
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn owned_by(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// Insert the todo and its tags, returning the new ID
+    pub async fn insert(self, pool: &PgPool) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO todos
+                 (id, title, description, completed, completed_at, created_at, updated_at, user_id)
+             VALUES ($1, $2, $3, $4, CASE WHEN $4 THEN $5 END, $5, $5, $6)"
+        )
+        .bind(id)
+        .bind(&self.title)
+        .bind(&self.description)
+        .bind(self.completed)
+        .bind(self.created_at)
+        .bind(self.user_id)
+        .execute(pool)
+        .await
+        .expect("Failed to insert todo fixture");
+
+        for tag in &self.tags {
+            sqlx::query("INSERT INTO todo_tags (todo_id, tag) VALUES ($1, $2)")
+                .bind(id)
+                .bind(tag)
+                .execute(pool)
+                .await
+                .expect("Failed to insert tag fixture");
+        }
+
+        id
+    }
+}
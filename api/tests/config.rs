@@ -0,0 +1,63 @@
+use std::env;
+use std::time::Duration;
+
+use todo_app::config::Config;
+use todo_app::middleware::RateLimit;
+
+// Environment variables are shared by the whole process, so everything runs in one test
+#[test]
+fn from_env_reports_every_problem_at_once() {
+    env::remove_var("DATABASE_URL");
+    env::remove_var("HOST");
+    env::set_var("PORT", "eighty");
+    env::set_var("RATE_LIMIT_REQUESTS", "-1");
+    env::set_var("DATABASE_MIN_CONNECTIONS", "10");
+    env::set_var("KEEP_ALIVE_SECS", "5s");
+    env::set_var("SLOW_QUERY_MS", "500ms");
+    env::set_var("DATABASE_IDLE_TIMEOUT", "60");
+    env::set_var("DATABASE_IDLE_TIMEOUT_SECS", "60");
+    env::set_var("TRASH_RETENTION_DAYS", "30");
+    env::set_var("PURGE_DELETED_AFTER_DAYS", "30");
+
+    let err = Config::from_env().unwrap_err();
+    assert_eq!(
+        err.0,
+        [
+            "DATABASE_URL must be set",
+            "PORT must be a port number from 0 to 65535, got 'eighty'",
+            "KEEP_ALIVE_SECS must be a non-negative integer, got '5s'",
+            "DATABASE_MIN_CONNECTIONS (10) cannot exceed DATABASE_MAX_CONNECTIONS (5)",
+            "DATABASE_IDLE_TIMEOUT_SECS and DATABASE_IDLE_TIMEOUT are the same setting; \
+             set only one of them",
+            "SLOW_QUERY_MS must be a non-negative integer, got '500ms'",
+            "RATE_LIMIT_REQUESTS must be a non-negative integer, got '-1'",
+            "TRASH_RETENTION_DAYS and PURGE_DELETED_AFTER_DAYS are the same setting; \
+             set only one of them",
+        ]
+    );
+
+    env::set_var("DATABASE_URL", "postgres://localhost/todo_db");
+    env::set_var("PORT", "9000");
+    env::remove_var("RATE_LIMIT_REQUESTS");
+    env::remove_var("DATABASE_MIN_CONNECTIONS");
+    env::set_var("SLOW_QUERY_MS", "500");
+    env::set_var("KEEP_ALIVE_SECS", "75");
+    env::remove_var("DATABASE_IDLE_TIMEOUT_SECS");
+    env::set_var("DATABASE_MAX_LIFETIME", "300");
+    env::remove_var("PURGE_DELETED_AFTER_DAYS");
+    let config = Config::from_env().unwrap();
+    assert_eq!(config.port, 9000);
+    assert_eq!(config.bind_address(), "127.0.0.1:9000");
+    assert_eq!(config.keep_alive, Duration::from_secs(75));
+    assert_eq!(config.client_timeout, Duration::from_secs(5));
+    assert_eq!(config.database.slow_query, Some(Duration::from_millis(500)));
+    assert_eq!(config.database.max_lifetime, Some(Duration::from_secs(300)));
+    assert_eq!(config.database.idle_timeout, Some(Duration::from_secs(60)));
+    // The cleanup purges whatever has been in the trash longer than this
+    let cleanup = config.cleanup.unwrap();
+    assert_eq!(cleanup.purge_deleted_after(), Some(chrono::Duration::days(30)));
+    assert_eq!(
+        config.rate_limit,
+        Some(RateLimit { requests: 60, window: Duration::from_secs(60) })
+    );
+}
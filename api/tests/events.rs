@@ -0,0 +1,105 @@
+mod common;
+
+use actix_web::test;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use common::{TestContext, TodoFixture};
+use todo_app::db;
+use todo_app::events::{relay_notifications, Subscription, TodoEvent};
+use todo_app::middleware::CurrentUser;
+
+/// The relay only hears changes committed after its `LISTEN`, so wait until it is issued
+async fn wait_for_listener(pool: &PgPool) {
+    for _ in 0..100 {
+        let listening: bool = sqlx::query_scalar(
+            r#"SELECT EXISTS (
+                 SELECT 1 FROM pg_stat_activity WHERE query = 'LISTEN "todo_changes"'
+             )"#,
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        if listening {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("The relay never started listening");
+}
+
+async fn next_event(events: &mut Subscription) -> TodoEvent {
+    tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .expect("No event was relayed")
+        .expect("The bus closed")
+}
+
+#[actix_web::test]
+async fn changes_from_other_instances_are_relayed() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let mut events = ctx.bus.subscribe(CurrentUser(None));
+    let relay = actix_web::rt::spawn(relay_notifications(
+        ctx.pool.clone(),
+        ctx.bus.clone().into_inner(),
+    ));
+    wait_for_listener(&ctx.pool).await;
+
+    // A change made by this process is skipped, since its handler publishes it already
+    let mut tx = ctx.pool.begin().await.unwrap();
+    sqlx::query(&format!("SET LOCAL todo_app.instance TO '{}'", db::instance_id()))
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO todos (id, title) VALUES ($1, 'Made here')")
+        .bind(Uuid::new_v4())
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    // Test connections carry no instance, like another server's
+    let id = TodoFixture::new("Made elsewhere").insert(&ctx.pool).await;
+    match next_event(&mut events).await {
+        TodoEvent::Created { todo } => assert_eq!(todo.id, id),
+        other => panic!("Expected a created event, got {:?}", other),
+    }
+
+    sqlx::query("UPDATE todos SET deleted_at = now() WHERE id = $1")
+        .bind(id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    match next_event(&mut events).await {
+        TodoEvent::Deleted { id: deleted } => assert_eq!(deleted, id),
+        other => panic!("Expected a deleted event, got {:?}", other),
+    }
+
+    // Dropped here, the listener would outlive the test's runtime
+    relay.abort();
+    let _ = relay.await;
+}
+
+#[actix_web::test]
+async fn shutdown_ends_every_subscription_with_a_final_event() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let mut other_user = ctx.bus.subscribe(CurrentUser(Some(Uuid::new_v4())));
+    let req = test::TestRequest::get().uri("/api/todos/stream").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    ctx.bus.shutdown();
+    assert!(matches!(next_event(&mut other_user).await, TodoEvent::Shutdown));
+    assert!(other_user.recv().await.is_none());
+    // The event stream ends instead of waiting for the shutdown timeout
+    let body = tokio::time::timeout(Duration::from_secs(5), test::read_body(resp))
+        .await
+        .expect("The stream stayed open");
+    assert_eq!(body, "event: shutdown\ndata: {}\n\n");
+
+    // A client connecting while the server drains is told to go elsewhere right away
+    let mut late = ctx.bus.subscribe(CurrentUser(None));
+    assert!(matches!(next_event(&mut late).await, TodoEvent::Shutdown));
+    assert!(late.recv().await.is_none());
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+use actix_web::middleware::{from_fn, Compress};
+use actix_web::{test, web};
+use serde_json::json;
+use std::time::Duration;
+
+use common::{read_json, TestContext, TodoFixture};
+use todo_app::middleware::{
+    log_bodies, pretty_json, rate_limit, skip_small_bodies, track_metrics, BodyLogging, Metrics,
+    PrettyJson, RateLimiter, RequestTimeout,
+};
+use todo_app::routes::ApiBasePath;
+
+#[actix_web::test]
+async fn slow_request_times_out_and_rolls_back() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let timeout = RequestTimeout::new(Duration::from_millis(200));
+    let app = test::init_service(ctx.app().app_data(web::Data::new(timeout))).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    // Hold the row lock so the PATCH blocks until the deadline
+    let mut blocker = ctx.pool.begin().await.unwrap();
+    sqlx::query("SELECT 1 FROM todos WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .execute(&mut *blocker)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", id))
+        .set_json(json!({ "title": "Buy bread" }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body["error"], "GATEWAY_TIMEOUT");
+    blocker.rollback().await.unwrap();
+
+    let req = test::TestRequest::get().uri(&format!("/api/todos/{}", id)).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Buy milk");
+}
+
+#[actix_web::test]
+async fn base_path_prefixes_every_route() {
+    let Some(ctx) = TestContext::new().await else { return };
+    assert!(ApiBasePath::parse("todo-service").is_err());
+    let base = ApiBasePath::parse("/todo-service/").unwrap();
+    let limiter = web::Data::new(RateLimiter::new(1, Duration::from_secs(60)));
+    let app = ctx.app_at(base).app_data(limiter).wrap(from_fn(rate_limit));
+    let app = test::init_service(app).await;
+    let peer = "10.0.0.1:4000".parse().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/todo-service/api/todos")
+        .peer_addr(peer)
+        .set_json(json!({ "title": "Buy milk" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let location = resp.headers().get("location").unwrap().to_str().unwrap().to_string();
+    let (status, created) = read_json(resp).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(location, format!("/todo-service/api/todos/{}", created["id"].as_str().unwrap()));
+
+    // The health check is still exempt from the limit under the prefix
+    for _ in 0..2 {
+        let req = test::TestRequest::get().uri("/todo-service/health").peer_addr(peer).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+    let req = test::TestRequest::get().uri("/todo-service/api/todos").peer_addr(peer).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "Route not found");
+
+    let req = test::TestRequest::get().uri("/todo-service/api-docs/openapi.json").to_request();
+    let (status, doc) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(doc["servers"][0]["url"], "/todo-service");
+}
+
+#[actix_web::test]
+async fn metrics_count_requests_by_route_pattern() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let metrics = web::Data::new(Metrics::new());
+    let app = test::init_service(ctx.app().app_data(metrics).wrap(from_fn(track_metrics))).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    for uri in [format!("/api/todos/{}", id), "/api/todos/not-a-uuid".to_string()] {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let req = test::TestRequest::get().uri("/metrics").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    for line in [
+        r#"http_requests_total{method="GET",route="/api/todos/{id}",status="200"} 1"#,
+        r#"http_requests_total{method="GET",route="/api/todos/{id}",status="400"} 1"#,
+        r#"http_request_duration_seconds_count{method="GET",route="/api/todos/{id}"} 2"#,
+    ] {
+        assert!(body.contains(line), "missing {} in:\n{}", line, body);
+    }
+    assert!(body.contains("db_pool_connections{state=\"idle\"}"));
+}
+
+#[actix_web::test]
+async fn body_logging_hands_bodies_on_unchanged() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let logging = web::Data::new(BodyLogging::new(16));
+    let app = test::init_service(ctx.app().app_data(logging).wrap(from_fn(log_bodies))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "A title well past the logging cap" }))
+        .to_request();
+    let (status, created) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["title"], "A title well past the logging cap");
+
+    let req = test::TestRequest::get().uri("/api/todos/export.csv").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert!(body.starts_with(b"id,title,"));
+}
+
+#[actix_web::test]
+async fn pretty_json_indents_without_reordering() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let title = r#"Say "hi", then {leave}: [now]"#;
+    let id = TodoFixture::new(title).tag("chat").insert(&ctx.pool).await;
+    let app = test::init_service(ctx.app().wrap(from_fn(pretty_json))).await;
+    let app = &app;
+    let get = |uri: String| async move {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(app, req).await;
+        let status = resp.status();
+        (status, String::from_utf8(test::read_body(resp).await.to_vec()).unwrap())
+    };
+
+    let (_, compact) = get(format!("/api/todos/{}", id)).await;
+    assert!(!compact.contains('\n'));
+    let (status, pretty) = get(format!("/api/todos/{}?pretty=true", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    // Same keys in the same order as the compact body, laid out like serde_json's pretty output
+    assert!(pretty.starts_with(&format!("{{\n  \"id\": \"{}\",\n  \"title\": ", id)));
+    assert!(pretty.contains("\"tags\": [\n    \"chat\"\n  ],"));
+    let parsed: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+    assert_eq!(parsed, serde_json::from_str::<serde_json::Value>(&compact).unwrap());
+    assert_eq!(parsed["title"], title);
+
+    let (_, empty) = get("/api/todos?tag=none&pretty=true".to_string()).await;
+    assert_eq!(empty, "[]");
+    let (status, _) = get("/api/todos?pretty=maybe".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // PRETTY_JSON makes it the default, which ?pretty=false turns off again
+    let app = ctx.app().app_data(web::Data::new(PrettyJson)).wrap(from_fn(pretty_json));
+    let app = test::init_service(app).await;
+    let req = test::TestRequest::get().uri("/api/todos/stats").to_request();
+    let body = test::read_body(test::call_service(&app, req).await).await;
+    assert!(body.starts_with(b"{\n  \"total\": 1,"));
+    let req = test::TestRequest::get().uri("/api/todos/stats?pretty=false").to_request();
+    let body = test::read_body(test::call_service(&app, req).await).await;
+    assert!(body.starts_with(b"{\"total\":1,"));
+}
+
+#[actix_web::test]
+async fn compression_skips_small_bodies() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(
+        ctx.app()
+            .wrap(from_fn(skip_small_bodies))
+            .wrap(Compress::default()),
+    )
+    .await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    for n in 0..20 {
+        TodoFixture::new(&format!("Todo {}", n)).insert(&ctx.pool).await;
+    }
+
+    let encoding = |uri: String| {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header((ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = test::call_service(app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            resp.headers().get(CONTENT_ENCODING).unwrap().to_str().unwrap().to_string()
+        }
+    };
+    assert_eq!(encoding(format!("/api/todos/{}", id)).await, "identity");
+    assert_eq!(encoding("/api/todos?per_page=100".to_string()).await, "gzip");
+    // Streamed, so compressed whatever its length
+    assert_eq!(encoding("/api/todos/export.csv".to_string()).await, "gzip");
+}
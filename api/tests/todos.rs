@@ -0,0 +1,1855 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use chrono::{Duration, TimeZone, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use common::{read_json, TestContext, TodoFixture};
+use todo_app::error::MAX_JSON_PAYLOAD_BYTES;
+use todo_app::events::TodoEvent;
+use todo_app::handlers::{CompletedLock, IdempotencyKeyTtl, TodoLimit};
+use todo_app::middleware::CurrentUser;
+use todo_app::models::TODO_FIELDS;
+
+#[actix_web::test]
+async fn create_then_get_returns_the_todo() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Learn Rust", "tags": ["work", "learning"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let location = resp.headers().get("location").unwrap().to_str().unwrap().to_string();
+    let (status, created) = read_json(resp).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["title"], "Learn Rust");
+    assert_eq!(created["completed"], false);
+    assert_eq!(created["priority"], "medium");
+    assert_eq!(created["tags"], json!(["learning", "work"]));
+    assert_eq!(location, format!("/api/todos/{}", created["id"].as_str().unwrap()));
+
+    let req = test::TestRequest::get().uri(&location).to_request();
+    let (status, fetched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched, created);
+}
+
+#[actix_web::test]
+async fn create_rejects_an_empty_title() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "  " }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "VALIDATION");
+    assert_eq!(body["fields"][0]["field"], "title");
+}
+
+#[actix_web::test]
+async fn title_and_description_lengths_are_checked_at_the_limit() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+
+    // Characters are counted, not bytes
+    let longest_title = "é".repeat(255);
+    let longest_description = "d".repeat(10_000);
+    let accepted = [
+        json!({ "title": "a" }),
+        json!({ "title": longest_title }),
+        json!({ "title": "Buy milk", "description": longest_description }),
+    ];
+    let rejected = [
+        (json!({ "title": "é".repeat(256) }), "title", "255"),
+        (json!({ "title": "Buy milk", "description": "d".repeat(10_001) }), "description", "10000"),
+    ];
+    // A PUT needs `completed` as well
+    let writes = || {
+        [
+            (test::TestRequest::post().uri("/api/todos"), false),
+            (test::TestRequest::patch().uri(&uri), false),
+            (test::TestRequest::put().uri(&uri), true),
+        ]
+    };
+
+    for body in &accepted {
+        for (req, replace) in writes() {
+            let mut body = body.clone();
+            if replace {
+                body["completed"] = json!(false);
+            }
+            let (status, todo) =
+                read_json(test::call_service(&app, req.set_json(&body).to_request()).await).await;
+            assert!(status.is_success(), "{} for {}", status, body["title"]);
+            assert_eq!(todo["title"], body["title"]);
+        }
+    }
+    for (body, field, limit) in &rejected {
+        for (req, replace) in writes() {
+            let mut body = body.clone();
+            if replace {
+                body["completed"] = json!(false);
+            }
+            let (status, error) =
+                read_json(test::call_service(&app, req.set_json(&body).to_request()).await).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error["fields"][0]["field"], *field);
+            let message = error["fields"][0]["message"].as_str().unwrap();
+            assert!(message.contains(limit), "{}", message);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn unreadable_bodies_are_json_bad_requests() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    let cases = [
+        (test::TestRequest::post().uri("/api/todos"), r#"{"title": "Buy"#, "not valid JSON"),
+        (
+            test::TestRequest::post().uri("/api/todos"),
+            r#"{"description": "x"}"#,
+            "missing field `title`",
+        ),
+        (
+            test::TestRequest::patch().uri(&format!("/api/todos/{}", id)),
+            r#"{"completed": "yes"}"#,
+            "expected a boolean",
+        ),
+    ];
+    for (req, body, expected) in cases {
+        let req = req
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(body)
+            .to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "BAD_REQUEST");
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains(expected), "{}", message);
+    }
+
+    for content_type in [Some("text/plain"), None] {
+        let mut req = test::TestRequest::post().uri("/api/todos");
+        if let Some(content_type) = content_type {
+            req = req.insert_header(("Content-Type", content_type));
+        }
+        let req = req.set_payload(r#"{"title": "Buy milk"}"#).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "BAD_REQUEST");
+        assert_eq!(body["message"], "Content-Type must be application/json");
+    }
+}
+
+#[actix_web::test]
+async fn oversized_bodies_are_payload_too_large() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let description = "d".repeat(MAX_JSON_PAYLOAD_BYTES);
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Buy milk", "description": description }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["error"], "PAYLOAD_TOO_LARGE");
+    assert_eq!(
+        body["message"],
+        format!("Request body exceeds the {} byte limit", MAX_JSON_PAYLOAD_BYTES)
+    );
+}
+
+#[actix_web::test]
+async fn create_rejects_server_set_fields() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    for field in ["id", "created_at", "updated_at"] {
+        let mut body = json!({ "title": "Buy milk" });
+        body[field] = json!("2020-01-01T00:00:00Z");
+        let req = test::TestRequest::post().uri("/api/todos").set_json(body).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["message"].as_str().unwrap().contains(&format!("unknown field `{}`", field)));
+    }
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[actix_web::test]
+async fn misspelled_fields_are_rejected() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    let requests = [
+        test::TestRequest::post().uri("/api/todos"),
+        test::TestRequest::patch().uri(&format!("/api/todos/{}", id)),
+        test::TestRequest::put().uri(&format!("/api/todos/{}", id)),
+    ];
+    for req in requests {
+        let req = req.set_json(json!({ "titel": "Buy bread" })).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("unknown field `titel`"), "{}", message);
+        assert!(message.contains("`title`"), "{}", message);
+    }
+
+    // A PUT replaces everything, so a typo would otherwise clear the field it meant to set
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/todos/{}", id))
+        .set_json(json!({ "title": "x", "completed": false, "descripton": "y" }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("unknown field `descripton`"), "{}", message);
+
+    let req = test::TestRequest::get().uri(&format!("/api/todos/{}", id)).to_request();
+    let (_, todo) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(todo["title"], "Buy milk");
+}
+
+#[actix_web::test]
+async fn patch_and_put_update_the_todo() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").description("2 litres").insert(&ctx.pool).await;
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", id))
+        .set_json(json!({ "completed": true }))
+        .to_request();
+    let (status, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(patched["completed"], true);
+    assert_eq!(patched["description"], "2 litres");
+    assert!(patched["completed_at"].is_string());
+    assert_eq!(patched["version"], 2);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/todos/{}", id))
+        .set_json(json!({ "title": "Buy oat milk", "completed": false }))
+        .to_request();
+    let (status, replaced) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(replaced["title"], "Buy oat milk");
+    assert_eq!(replaced["description"], json!(null));
+    assert_eq!(replaced["completed_at"], json!(null));
+    assert_eq!(replaced["version"], 3);
+}
+
+#[actix_web::test]
+async fn patch_sets_clears_or_keeps_the_description() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").description("2 litres").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+
+    let steps = [
+        (json!({ "description": "1 litre" }), json!("1 litre")),
+        // An absent key is not the same as `null`: it leaves the description alone
+        (json!({ "title": "Buy oat milk" }), json!("1 litre")),
+        (json!({ "description": null }), json!(null)),
+        (json!({ "completed": true }), json!(null)),
+    ];
+    for (patch, description) in steps {
+        let req = test::TestRequest::patch().uri(&uri).set_json(&patch).to_request();
+        let (status, todo) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(todo["description"], description, "after {}", patch);
+    }
+}
+
+#[actix_web::test]
+async fn completed_at_is_set_only_when_a_todo_is_completed() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+    let patch = |body: serde_json::Value| {
+        test::TestRequest::patch().uri(&uri).set_json(body).to_request()
+    };
+
+    let resp = test::call_service(&app, patch(json!({ "completed": true }))).await;
+    let (_, todo) = read_json(resp).await;
+    let completed_at = todo["completed_at"].clone();
+    assert!(completed_at.is_string());
+
+    // Neither other edits nor completing it again move the timestamp
+    let edits = [
+        json!({ "title": "Buy oat milk" }),
+        json!({ "completed": true, "priority": "high" }),
+    ];
+    for body in edits {
+        let (status, todo) = read_json(test::call_service(&app, patch(body)).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(todo["completed_at"], completed_at);
+    }
+
+    let resp = test::call_service(&app, patch(json!({ "completed": false }))).await;
+    let (_, todo) = read_json(resp).await;
+    assert_eq!(todo["completed_at"], json!(null));
+}
+
+#[actix_web::test]
+async fn stale_version_is_a_conflict() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    for (version, expected) in [(1, StatusCode::OK), (1, StatusCode::CONFLICT)] {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/todos/{}", id))
+            .set_json(json!({ "title": "Buy bread", "version": version }))
+            .to_request();
+        let (status, _) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, expected);
+    }
+}
+
+#[actix_web::test]
+async fn updates_that_change_nothing_are_not_written() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let long_ago = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    let id = TodoFixture::new("Buy milk")
+        .tag("groceries")
+        .created_at(long_ago)
+        .insert(&ctx.pool)
+        .await;
+    let uri = format!("/api/todos/{}", id);
+
+    let unchanged = [
+        test::TestRequest::patch()
+            .uri(&uri)
+            .set_json(json!({ "title": "Buy milk", "tags": ["groceries", "groceries"] })),
+        test::TestRequest::put()
+            .uri(&uri)
+            .set_json(json!({ "title": "Buy milk", "completed": false, "tags": ["groceries"] })),
+    ];
+    for req in unchanged {
+        let (status, body) = read_json(test::call_service(&app, req.to_request()).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["version"], 1);
+        assert_eq!(body["updated_at"], "2024-01-15T10:00:00Z");
+    }
+
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "title": "Buy milk", "priority": "high" }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["version"], 2);
+    assert_ne!(body["updated_at"], "2024-01-15T10:00:00Z");
+}
+
+#[actix_web::test]
+async fn merge_patch_clears_nulls_and_rejects_read_only_fields() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk")
+        .description("2 litres")
+        .tag("groceries")
+        .insert(&ctx.pool)
+        .await;
+    let merge_patch = |body: serde_json::Value| {
+        test::TestRequest::patch()
+            .uri(&format!("/api/todos/{}", id))
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(body.to_string())
+            .to_request()
+    };
+
+    let req = merge_patch(json!({ "title": "Buy oat milk", "description": null, "tags": null }));
+    let (status, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(patched["title"], "Buy oat milk");
+    assert_eq!(patched["description"], json!(null));
+    assert_eq!(patched["tags"], json!([]));
+
+    let req = merge_patch(json!({ "id": Uuid::new_v4(), "created_at": null, "title": null }));
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let fields: Vec<_> = body["fields"].as_array().unwrap().iter().map(|f| &f["field"]).collect();
+    assert_eq!(fields, ["created_at", "id", "title"]);
+}
+
+#[actix_web::test]
+async fn metadata_must_be_an_object_and_merges_on_merge_patch() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Buy milk", "metadata": ["https://example.com"] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["fields"][0]["field"], "metadata");
+
+    let metadata = json!({ "links": ["https://example.com/list"], "store": "corner shop" });
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Buy milk", "metadata": metadata }))
+        .to_request();
+    let (status, created) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["metadata"], metadata);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", created["id"].as_str().unwrap()))
+        .insert_header(("Content-Type", "application/merge-patch+json"))
+        .set_payload(json!({ "metadata": { "store": null, "aisle": 4 } }).to_string())
+        .to_request();
+    let (status, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(patched["metadata"], json!({ "links": ["https://example.com/list"], "aisle": 4 }));
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", created["id"].as_str().unwrap()))
+        .set_json(json!({ "metadata": { "aisle": 5 } }))
+        .to_request();
+    let (_, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(patched["metadata"], json!({ "aisle": 5 }));
+}
+
+#[actix_web::test]
+async fn tags_are_normalized_on_every_write() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({
+            "title": "Plan",
+            "tags": ["Work", " work ", "work", "", "  ", "\tHome"],
+        }))
+        .to_request();
+    let (status, created) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["tags"], json!(["home", "work"]));
+    let uri = format!("/api/todos/{}", created["id"].as_str().unwrap());
+
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "tags": ["URGENT", "urgent ", "Work"] }))
+        .to_request();
+    let (status, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(patched["tags"], json!(["urgent", "work"]));
+
+    // The same tags in another spelling are no change at all
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .set_json(json!({ "title": "Plan", "completed": false, "tags": ["Work", "URGENT"] }))
+        .to_request();
+    let (status, replaced) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(replaced["version"], patched["version"]);
+
+    let req = test::TestRequest::get().uri("/api/todos?tag=%20Urgent").to_request();
+    let (_, list) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+
+    let long = format!("  {}  ", "x".repeat(51));
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "tags": ["ok", long] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["fields"][0]["field"], "tags[1]");
+}
+
+#[actix_web::test]
+async fn colors_are_validated_and_filterable() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let create = |title: &str, color: &str| {
+        test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title, "color": color }))
+            .to_request()
+    };
+
+    for color in ["#12345", "#gggggg", "teal"] {
+        let (status, body) = read_json(test::call_service(&app, create("Bad", color)).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "color");
+    }
+    let (status, hex) = read_json(test::call_service(&app, create("Hex", "#1E90FF")).await).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(hex["color"], "#1e90ff");
+    let (_, named) = read_json(test::call_service(&app, create("Named", "Blue")).await).await;
+    assert_eq!(named["color"], "blue");
+
+    let req = test::TestRequest::get().uri("/api/todos?color=BLUE").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["id"], named["id"]);
+    let req = test::TestRequest::get().uri("/api/todos?color=teal").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", hex["id"].as_str().unwrap()))
+        .set_json(json!({ "color": null }))
+        .to_request();
+    let (_, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(patched["color"], json!(null));
+}
+
+#[actix_web::test]
+async fn time_tracking_is_validated_and_summed_in_stats() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Bad", "estimated_minutes": -5, "actual_minutes": -1 }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["fields"][0]["field"], "estimated_minutes");
+    assert_eq!(body["fields"][1]["field"], "actual_minutes");
+
+    let mut ids = Vec::new();
+    let todos = [("Write report", 60, 90), ("Review", 30, 20), ("Plan", 45, 0)];
+    for (title, estimated, actual) in todos {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title, "estimated_minutes": estimated }))
+            .to_request();
+        let (status, created) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["estimated_minutes"], estimated);
+        assert_eq!(created["actual_minutes"], json!(null));
+        ids.push((created["id"].as_str().unwrap().to_string(), actual));
+    }
+    // The first two are finished; the third stays pending and out of the sums
+    for (id, actual) in &ids[..2] {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/todos/{}", id))
+            .set_json(json!({ "completed": true, "actual_minutes": actual }))
+            .to_request();
+        let (status, patched) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(patched["actual_minutes"], *actual);
+    }
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", ids[2].0))
+        .set_json(json!({ "actual_minutes": -10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get().uri("/api/todos/stats").to_request();
+    let (status, stats) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stats["completed"], 2);
+    assert_eq!(stats["completed_estimated_minutes"], 90);
+    assert_eq!(stats["completed_actual_minutes"], 110);
+}
+
+#[actix_web::test]
+async fn delete_hides_the_todo_until_restored() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Walk the dog").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+
+    // Only a deleted todo can be restored
+    let req = test::TestRequest::post().uri(&format!("{}/restore", uri)).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "CONFLICT");
+
+    let req = test::TestRequest::delete().uri(&uri).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(body, json!(null));
+
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "NOT_FOUND");
+
+    let req = test::TestRequest::post().uri(&format!("{}/restore", uri)).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted_at"], json!(null));
+
+    let req = test::TestRequest::delete().uri(&format!("{}?hard=true", uri)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::post().uri(&format!("{}/restore", uri)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn history_lists_each_version_with_its_changes() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").tag("groceries").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "title": "Buy oat milk", "tags": ["groceries", "vegan"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::post().uri(&format!("{}/toggle", uri)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::delete().uri(&uri).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get().uri(&format!("{}/history", uri)).to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    let entries = body.as_array().unwrap();
+    let versions: Vec<_> = entries.iter().map(|e| e["version"].as_i64().unwrap()).collect();
+    assert_eq!(versions, [1, 2, 3, 4]);
+    assert_eq!(entries[0]["changes"], json!({}));
+    assert_eq!(
+        entries[1]["changes"],
+        json!({
+            "tags": { "old": ["groceries"], "new": ["groceries", "vegan"] },
+            "title": { "old": "Buy milk", "new": "Buy oat milk" },
+        })
+    );
+    let changed = |entry: &serde_json::Value| -> Vec<String> {
+        entry["changes"].as_object().unwrap().keys().cloned().collect()
+    };
+    assert_eq!(changed(&entries[2]), ["completed", "completed_at"]);
+    assert_eq!(changed(&entries[3]), ["deleted_at"]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos/{}/history", Uuid::new_v4()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn invalid_id_is_a_bad_request() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let req = test::TestRequest::get().uri("/api/todos/not-a-uuid").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body,
+        json!({ "error": "BAD_REQUEST", "message": "Invalid todo ID format" })
+    );
+}
+
+#[actix_web::test]
+async fn exhausted_pool_is_a_retryable_503() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(std::time::Duration::from_secs(1))
+        .connect(&std::env::var("TEST_DATABASE_URL").unwrap())
+        .await
+        .unwrap();
+    let app = test::init_service(ctx.app().app_data(web::Data::new(pool.clone()))).await;
+    let _held = pool.acquire().await.unwrap();
+
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "1");
+    let (status, body) = read_json(resp).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"], "SERVICE_UNAVAILABLE");
+}
+
+#[actix_web::test]
+async fn unknown_routes_get_a_json_404() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    for uri in ["/nowhere", "/api/todos/not/a/route"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+        assert_eq!(body, json!({ "error": "NOT_FOUND", "message": "Route not found" }));
+    }
+}
+
+#[actix_web::test]
+async fn pages_are_stable_when_created_at_ties() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let created_at = Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap();
+    for i in 0..6 {
+        TodoFixture::new(&format!("Imported {}", i))
+            .created_at(created_at)
+            .insert(&ctx.pool)
+            .await;
+    }
+
+    // Walk the list two at a time, twice; both walks must see every todo exactly once
+    // and in the same order
+    let mut walks = Vec::new();
+    for _ in 0..2 {
+        let mut ids = Vec::new();
+        for offset in (0..6).step_by(2) {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/todos?limit=2&offset={}", offset))
+                .to_request();
+            let (status, page) = read_json(test::call_service(&app, req).await).await;
+            assert_eq!(status, StatusCode::OK);
+            ids.extend(page.as_array().unwrap().iter().map(|t| t["id"].clone()));
+        }
+        walks.push(ids);
+    }
+
+    let mut unique = walks[0].clone();
+    unique.sort_by_key(|id| id.to_string());
+    unique.dedup();
+    assert_eq!(unique.len(), 6);
+    assert_eq!(walks[0], walks[1]);
+}
+
+#[actix_web::test]
+async fn cursor_pages_are_unaffected_by_new_todos() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    // Two share a timestamp, so the walk has to fall back on the ID
+    for (title, day) in [("A", 1), ("B", 2), ("C", 3), ("D", 3), ("E", 4)] {
+        TodoFixture::new(title)
+            .created_at(Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap())
+            .insert(&ctx.pool)
+            .await;
+    }
+
+    let mut titles = Vec::new();
+    let mut cursors = Vec::new();
+    let mut uri = "/api/todos?limit=2".to_string();
+    loop {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        let next = resp
+            .headers()
+            .get("x-next-cursor")
+            .map(|cursor| cursor.to_str().unwrap().to_string());
+        let (status, page) = read_json(resp).await;
+        assert_eq!(status, StatusCode::OK);
+        titles.extend(page.as_array().unwrap().iter().map(|t| t["title"].clone()));
+
+        // With offsets this would push a todo already seen onto the next page
+        TodoFixture::new("Newer").insert(&ctx.pool).await;
+        let Some(cursor) = next else { break };
+        uri = format!("/api/todos?limit=2&cursor={}", cursor);
+        cursors.push(cursor);
+    }
+    assert_eq!(titles.len(), 5);
+    assert_eq!(titles[0], "E");
+    assert_eq!(titles[4], "A");
+
+    assert_eq!(cursors.len(), 2);
+
+    for query in [
+        "cursor=not-a-cursor".to_string(),
+        format!("cursor={}&offset=2", cursors[0]),
+        format!("cursor={}&sort_by=title", cursors[0]),
+    ] {
+        let uri = format!("/api/todos?{}", query);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let (status, _) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn ascending_cursor_walk_visits_oldest_first() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    for (title, day) in [("B", 2), ("A", 1), ("C", 3), ("D", 3), ("E", 4)] {
+        TodoFixture::new(title)
+            .created_at(Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap())
+            .insert(&ctx.pool)
+            .await;
+    }
+
+    let mut walked = Vec::new();
+    let mut uri = "/api/todos?order=asc&limit=2".to_string();
+    loop {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        let next = resp
+            .headers()
+            .get("x-next-cursor")
+            .map(|cursor| cursor.to_str().unwrap().to_string());
+        let (status, page) = read_json(resp).await;
+        assert_eq!(status, StatusCode::OK);
+        for todo in page.as_array().unwrap() {
+            walked.push(todo["id"].as_str().unwrap().parse::<Uuid>().unwrap());
+        }
+
+        // Created mid-walk, so it is the last todo the walk reaches
+        if walked.len() == 2 {
+            TodoFixture::new("Newer").insert(&ctx.pool).await;
+        }
+        let Some(cursor) = next else { break };
+        uri = format!("/api/todos?order=asc&limit=2&cursor={}", cursor);
+    }
+
+    let oldest_first: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM todos ORDER BY created_at, id")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(walked.len(), 6);
+    assert_eq!(walked, oldest_first);
+}
+
+#[actix_web::test]
+async fn completed_filter_splits_the_list() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let start = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    for (hours, title, completed) in
+        [(0, "a", true), (1, "b", false), (2, "c", true), (3, "d", false), (4, "e", false)]
+    {
+        let todo = TodoFixture::new(title).created_at(start + Duration::hours(hours));
+        let todo = if completed { todo.completed() } else { todo };
+        todo.insert(&ctx.pool).await;
+    }
+
+    for (query, expected, total) in [
+        ("?completed=true", vec!["c", "a"], "2"),
+        ("?completed=false", vec!["e", "d", "b"], "3"),
+        ("", vec!["e", "d", "c", "b", "a"], "5"),
+    ] {
+        let req = test::TestRequest::get().uri(&format!("/api/todos{}", query)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("x-total-count").unwrap(), total);
+        let (status, body) = read_json(resp).await;
+        assert_eq!(status, StatusCode::OK);
+        let titles: Vec<_> = body.as_array().unwrap().iter().map(|t| &t["title"]).collect();
+        assert_eq!(titles, expected, "{}", query);
+    }
+
+    let req = test::TestRequest::get().uri("/api/todos?completed=maybe").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn pending_first_sinks_completed_todos() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let start = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    for (hours, title, completed) in
+        [(0, "a", false), (1, "b", true), (2, "c", false), (3, "d", true)]
+    {
+        let todo = TodoFixture::new(title).created_at(start + Duration::hours(hours));
+        let todo = if completed { todo.completed() } else { todo };
+        todo.insert(&ctx.pool).await;
+    }
+
+    let req = test::TestRequest::get().uri("/api/todos?pending_first=true").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    let titles: Vec<_> = body.as_array().unwrap().iter().map(|t| &t["title"]).collect();
+    assert_eq!(titles, ["c", "a", "d", "b"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos?pending_first=true&limit=2")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().get("X-Next-Cursor").is_none());
+}
+
+#[actix_web::test]
+async fn changed_since_returns_edits_and_tombstones() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let long_ago = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    TodoFixture::new("Untouched").created_at(long_ago).insert(&ctx.pool).await;
+    let edited = TodoFixture::new("Edited").created_at(long_ago).insert(&ctx.pool).await;
+    let deleted = TodoFixture::new("Deleted").created_at(long_ago).insert(&ctx.pool).await;
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", edited))
+        .set_json(json!({ "completed": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::delete().uri(&format!("/api/todos/{}", deleted)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos?changed_since=2025-01-01T00:00:00Z&sort_by=title&order=asc")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let watermark = resp.headers().get("X-Sync-Watermark").unwrap().to_str().unwrap().to_string();
+    let (status, body) = read_json(resp).await;
+    assert_eq!(status, StatusCode::OK);
+    let todos = body.as_array().unwrap();
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[0]["title"], "Deleted");
+    assert!(todos[0]["deleted_at"].is_string());
+    assert_eq!(todos[1]["title"], "Edited");
+    assert_eq!(todos[1]["completed"], true);
+
+    // The watermark is set back from the read, so changes that recent come back unchanged
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos?changed_since={}&sort_by=title&order=asc", watermark))
+        .to_request();
+    let (status, repeated) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(repeated, body);
+
+    for uri in [
+        "/api/todos/board?changed_since=2025-01-01T00:00:00Z",
+        "/api/todos?changed_since=soon",
+        "/api/todos?changed_since=2025-01-01T00:00:00Z&completed=false",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[actix_web::test]
+async fn changed_since_reports_archived_todos() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let long_ago = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    let id = TodoFixture::new("Archived").created_at(long_ago).insert(&ctx.pool).await;
+
+    let req = test::TestRequest::post().uri(&format!("/api/todos/{}/archive", id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Without include_archived, or a syncing client would keep its stale copy
+    let req = test::TestRequest::get()
+        .uri("/api/todos?changed_since=2025-01-01T00:00:00Z")
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["id"], id.to_string());
+    assert_eq!(body[0]["archived"], true);
+
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(body, json!([]));
+}
+
+#[actix_web::test]
+async fn ids_lists_every_matching_todo_compactly() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let start = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    for hours in 0..25 {
+        let todo = TodoFixture::new("Buy milk").created_at(start + Duration::hours(hours));
+        ids.push(todo.insert(&ctx.pool).await);
+    }
+    TodoFixture::new("Done").completed().insert(&ctx.pool).await;
+
+    let req = test::TestRequest::get().uri("/api/todos/ids?completed=false").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    let stamps = body.as_array().unwrap();
+    let listed: Vec<Uuid> =
+        stamps.iter().map(|s| s["id"].as_str().unwrap().parse().unwrap()).collect();
+    assert_eq!(listed, ids);
+    assert_eq!(stamps[0], json!({ "id": ids[0], "updated_at": "2024-01-15T10:00:00Z" }));
+}
+
+#[actix_web::test]
+async fn trash_lists_deleted_todos_newest_first() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let kept = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let older = TodoFixture::new("Walk the dog").insert(&ctx.pool).await;
+    sqlx::query("UPDATE todos SET deleted_at = NOW() - INTERVAL '1 day' WHERE id = $1")
+        .bind(older)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    let newer = TodoFixture::new("Call mom").insert(&ctx.pool).await;
+    let req = test::TestRequest::delete().uri(&format!("/api/todos/{}", newer)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let ids = |body: &serde_json::Value| -> Vec<Uuid> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| todo["id"].as_str().unwrap().parse().unwrap())
+            .collect()
+    };
+    let req = test::TestRequest::get().uri("/api/todos/trash").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("x-total-count").unwrap(), "2");
+    let (status, body) = read_json(resp).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ids(&body), [newer, older]);
+    assert!(body[0]["deleted_at"].is_string());
+
+    let req = test::TestRequest::get().uri("/api/todos").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(ids(&body), [kept]);
+
+    let req = test::TestRequest::get().uri("/api/todos/trash?limit=1&offset=1").to_request();
+    let resp = test::call_service(&app, req).await;
+    let link = resp.headers().get("link").unwrap().to_str().unwrap().to_string();
+    assert!(link.contains("rel=\"prev\""), "{}", link);
+    let (_, body) = read_json(resp).await;
+    assert_eq!(ids(&body), [older]);
+
+    // Restoring takes a todo out of the trash
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/todos/{}/restore", older))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get().uri("/api/todos/trash").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(ids(&body), [newer]);
+}
+
+#[actix_web::test]
+async fn autocomplete_matches_title_prefixes() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    for title in ["Buy milk", "buy bread", "Call mom", "Go buy eggs", "Buy_100% juice"] {
+        TodoFixture::new(title).insert(&ctx.pool).await;
+    }
+    let gone = TodoFixture::new("Buy stamps").insert(&ctx.pool).await;
+    sqlx::query("UPDATE todos SET deleted_at = NOW() WHERE id = $1")
+        .bind(gone)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let titles = |body: &serde_json::Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| todo["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let req = test::TestRequest::get().uri("/api/todos/autocomplete?q=BUY").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    // Where `_` sorts depends on the database's collation
+    let mut matched = titles(&body);
+    matched.sort();
+    assert_eq!(matched, ["Buy milk", "Buy_100% juice", "buy bread"]);
+    assert_eq!(body[0].as_object().unwrap().len(), 2);
+
+    // LIKE wildcards in the prefix are matched literally
+    let req = test::TestRequest::get().uri("/api/todos/autocomplete?q=buy_").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(titles(&body), ["Buy_100% juice"]);
+
+    let req = test::TestRequest::get().uri("/api/todos/autocomplete?q=buy&limit=1").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(titles(&body), ["buy bread"]);
+
+    for uri in ["/api/todos/autocomplete", "/api/todos/autocomplete?q=%20"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "q must not be blank");
+    }
+}
+
+#[actix_web::test]
+async fn ids_filter_fetches_several_todos_at_once() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let start = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    for hours in 0..25 {
+        let todo = TodoFixture::new("Buy milk").created_at(start + Duration::hours(hours));
+        ids.push(todo.insert(&ctx.pool).await);
+    }
+    let missing = Uuid::new_v4();
+
+    // More than a default page, in a different order, with one unknown ID
+    let mut wanted: Vec<Uuid> = ids.iter().rev().skip(1).copied().collect();
+    wanted.push(missing);
+    let list = wanted.iter().map(Uuid::to_string).collect::<Vec<_>>().join(",");
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos?ids={}&order=asc", list))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("x-total-count").unwrap(), "24");
+    let (status, body) = read_json(resp).await;
+    assert_eq!(status, StatusCode::OK);
+    let listed: Vec<Uuid> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|todo| todo["id"].as_str().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(listed, ids[..24]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos?ids={},not-a-uuid", ids[0]))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "ids must be comma-separated UUIDs, got 'not-a-uuid'");
+
+    let too_many = vec![missing.to_string(); 101].join(",");
+    let req = test::TestRequest::get().uri(&format!("/api/todos?ids={}", too_many)).to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn accept_text_plain_lists_todos_as_checkboxes() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let created_at = Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap();
+    TodoFixture::new("Buy milk").completed().created_at(created_at).insert(&ctx.pool).await;
+    TodoFixture::new("Walk the dog").insert(&ctx.pool).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos")
+        .insert_header(("Accept", "text/plain"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/plain; charset=utf-8");
+    let body = test::read_body(resp).await;
+    assert_eq!(body, "[ ] Walk the dog\n[x] Buy milk\n");
+
+    // JSON stays the default for wildcard and missing Accept headers
+    let req = test::TestRequest::get()
+        .uri("/api/todos")
+        .insert_header(("Accept", "*/*"))
+        .to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn repeated_idempotency_key_replays_the_create() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let mut ids = Vec::new();
+    for key in ["retry-1", "retry-1", "retry-2"] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .insert_header(("Idempotency-Key", key))
+            .set_json(json!({ "title": "Buy milk" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let replayed = resp.headers().contains_key("idempotent-replayed");
+        let (status, body) = read_json(resp).await;
+        assert_eq!(status, StatusCode::CREATED);
+        ids.push((body["id"].clone(), replayed));
+    }
+
+    assert_eq!(ids[0].0, ids[1].0);
+    assert_ne!(ids[0].0, ids[2].0);
+    assert_eq!([ids[0].1, ids[1].1, ids[2].1], [false, true, false]);
+}
+
+#[actix_web::test]
+async fn expired_idempotency_key_creates_again() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let ttl = IdempotencyKeyTtl::new(Duration::zero());
+    let app = test::init_service(ctx.app().app_data(web::Data::new(ttl))).await;
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .insert_header(("Idempotency-Key", "retry-1"))
+            .set_json(json!({ "title": "Buy milk" }))
+            .to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        ids.push(body["id"].clone());
+    }
+    assert_ne!(ids[0], ids[1]);
+}
+
+#[actix_web::test]
+async fn locked_completed_todos_can_only_be_reopened() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app().app_data(web::Data::new(CompletedLock))).await;
+    let id = TodoFixture::new("Buy milk").completed().insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}", id);
+
+    let edits = [
+        test::TestRequest::patch().uri(&uri).set_json(json!({ "title": "Buy oat milk" })),
+        // Reopening in the same request doesn't unlock the other fields
+        test::TestRequest::patch()
+            .uri(&uri)
+            .set_json(json!({ "completed": false, "priority": "high" })),
+        test::TestRequest::put()
+            .uri(&uri)
+            .set_json(json!({ "title": "Buy milk", "completed": true, "tags": ["groceries"] })),
+    ];
+    for req in edits {
+        let (status, body) = read_json(test::call_service(&app, req.to_request()).await).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body["message"],
+            format!("Todo with id {} is completed; reopen it before editing it", id)
+        );
+    }
+
+    // Writing back what is stored changes nothing, so it is allowed
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .set_json(json!({ "title": "Buy milk", "completed": true }))
+        .to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "completed": false }))
+        .to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let req = test::TestRequest::patch()
+        .uri(&uri)
+        .set_json(json!({ "title": "Buy oat milk" }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Buy oat milk");
+}
+
+#[actix_web::test]
+async fn creates_past_max_todos_are_conflicts() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let limit = TodoLimit::new(2);
+    let app = test::init_service(ctx.app().app_data(web::Data::new(limit))).await;
+    TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    // One slot is left, so a batch of two fails as a whole
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch")
+        .set_json(json!([{ "title": "Buy eggs" }, { "title": "Buy bread" }]))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "Todo limit of 2 reached; delete some todos before adding more");
+
+    for expected in [StatusCode::CREATED, StatusCode::CONFLICT] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": "Buy eggs" }))
+            .to_request();
+        let (status, _) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, expected);
+    }
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+}
+
+#[actix_web::test]
+async fn restores_past_max_todos_are_conflicts() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let limit = TodoLimit::new(1);
+    let app = test::init_service(ctx.app().app_data(web::Data::new(limit))).await;
+    let deleted = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let req = test::TestRequest::delete().uri(&format!("/api/todos/{}", deleted)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    // The deleted todo freed its slot for a new one, so it cannot take it back
+    let req = test::TestRequest::post()
+        .uri("/api/todos")
+        .set_json(json!({ "title": "Buy eggs" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/todos/{}/restore", deleted))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "Todo limit of 1 reached; delete some todos before adding more");
+
+    let live: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(live, 1);
+}
+
+#[actix_web::test]
+async fn dedupe_rejects_an_open_todo_with_the_same_title() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    TodoFixture::new("Walk the dog").completed().insert(&ctx.pool).await;
+
+    for (uri, title, expected) in [
+        ("/api/todos?dedupe=true", "BUY MILK", StatusCode::CONFLICT),
+        ("/api/todos?dedupe=true", "walk the dog", StatusCode::CREATED),
+        ("/api/todos", "buy milk", StatusCode::CREATED),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, expected, "{} {}", uri, title);
+        if status == StatusCode::CONFLICT {
+            assert_eq!(body["error"], "CONFLICT");
+        }
+    }
+}
+
+#[actix_web::test]
+async fn duplicate_copies_the_todo_as_pending() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk")
+        .description("2 litres")
+        .tag("groceries")
+        .completed()
+        .created_at(Utc::now() - Duration::days(3))
+        .insert(&ctx.pool)
+        .await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/todos/{}/duplicate?suffix=true", id))
+        .to_request();
+    let (status, copy) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_ne!(copy["id"], json!(id));
+    assert_eq!(copy["title"], "Buy milk (copy)");
+    assert_eq!(copy["description"], "2 litres");
+    assert_eq!(copy["tags"], json!(["groceries"]));
+    assert_eq!(copy["completed"], false);
+    let created_at: chrono::DateTime<Utc> = copy["created_at"].as_str().unwrap().parse().unwrap();
+    assert!(Utc::now() - created_at < Duration::minutes(1));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/todos/{}/duplicate", Uuid::new_v4()))
+        .to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn toggle_flips_completed_back_and_forth() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let uri = format!("/api/todos/{}/toggle", id);
+
+    let req = test::TestRequest::post().uri(&uri).to_request();
+    let (status, toggled) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(toggled["completed"], true);
+    assert!(toggled["completed_at"].is_string());
+    assert_eq!(toggled["version"], 2);
+
+    let req = test::TestRequest::post().uri(&uri).to_request();
+    let (_, toggled) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(toggled["completed"], false);
+    assert_eq!(toggled["completed_at"], json!(null));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/todos/{}/toggle", Uuid::new_v4()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn move_reorders_the_position_list() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let mut ids = Vec::new();
+    for title in ["A", "B", "C"] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let (_, body) = read_json(test::call_service(&app, req).await).await;
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+
+    for (id, index) in [(&ids[2], 0), (&ids[0], 99)] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/todos/{}/position", id))
+            .set_json(json!({ "index": index }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos?sort_by=position&order=asc")
+        .to_request();
+    let (_, list) = read_json(test::call_service(&app, req).await).await;
+    let titles: Vec<_> = list.as_array().unwrap().iter().map(|t| t["title"].clone()).collect();
+    assert_eq!(titles, [json!("C"), json!("B"), json!("A")]);
+}
+
+#[actix_web::test]
+async fn reorder_sets_many_positions_at_once() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    let mut ids = Vec::new();
+    for title in ["A", "B", "C", "D"] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let (_, body) = read_json(test::call_service(&app, req).await).await;
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    let reorder = |body: serde_json::Value| {
+        test::TestRequest::put().uri("/api/todos/order").set_json(body).to_request()
+    };
+    let list = || {
+        test::TestRequest::get().uri("/api/todos?sort_by=position&order=asc").to_request()
+    };
+    let summary = |list: &serde_json::Value| -> Vec<(String, i64)> {
+        let todos = list.as_array().unwrap();
+        todos
+            .iter()
+            .map(|t| (t["title"].as_str().unwrap().to_string(), t["version"].as_i64().unwrap()))
+            .collect()
+    };
+
+    // The listed todos swap the places they held; B and D stay where they are
+    let req = reorder(json!({ "ids": [ids[2], ids[0]] }));
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary(&body), [("C".to_string(), 2), ("A".to_string(), 2)]);
+    let (_, body) = read_json(test::call_service(&app, list()).await).await;
+    let titles: Vec<_> = summary(&body).into_iter().map(|(title, _)| title).collect();
+    assert_eq!(titles, ["C", "B", "A", "D"]);
+
+    // Everything unlisted follows the listed todos, in its current order
+    for _ in 0..2 {
+        let req = reorder(json!({ "ids": [ids[3], ids[1]], "unlisted": "end" }));
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+    let (_, body) = read_json(test::call_service(&app, list()).await).await;
+    let expected = [("D", 2), ("B", 2), ("C", 2), ("A", 2)];
+    let expected: Vec<_> = expected.iter().map(|&(t, v)| (t.to_string(), v)).collect();
+    assert_eq!(summary(&body), expected, "a repeated reorder changes nothing");
+
+    let req = reorder(json!({ "ids": [ids[0], ids[1], ids[0]] }));
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["fields"][0]["field"], "ids[2]");
+    let req = reorder(json!({ "ids": [] }));
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    let req = reorder(json!({ "ids": [ids[0], Uuid::new_v4()] }));
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn complete_all_respects_the_list_filters() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    TodoFixture::new("Buy milk").tag("groceries").insert(&ctx.pool).await;
+    TodoFixture::new("Buy eggs").tag("groceries").completed().insert(&ctx.pool).await;
+    TodoFixture::new("Walk the dog").insert(&ctx.pool).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos/complete-all?tag=groceries")
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "updated": 1 }));
+
+    let req = test::TestRequest::get().uri("/api/todos?completed=false").to_request();
+    let (_, pending) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(pending.as_array().unwrap().len(), 1);
+    assert_eq!(pending[0]["title"], "Walk the dog");
+
+    let req = test::TestRequest::post().uri("/api/todos/incomplete-all").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(body, json!({ "updated": 2 }));
+}
+
+#[actix_web::test]
+async fn board_pages_each_group_separately() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    for title in ["a", "b", "c"] {
+        TodoFixture::new(title).insert(&ctx.pool).await;
+        TodoFixture::new(&title.to_uppercase()).completed().insert(&ctx.pool).await;
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos/board?sort_by=title&order=asc&limit=2&offset=1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("X-Pending-Count").unwrap(), "3");
+    assert_eq!(resp.headers().get("X-Completed-Count").unwrap(), "3");
+    let (status, body) = read_json(resp).await;
+    assert_eq!(status, StatusCode::OK);
+    let titles = |group: &serde_json::Value| -> Vec<String> {
+        group.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().into()).collect()
+    };
+    assert_eq!(titles(&body["pending"]), ["b", "c"]);
+    assert_eq!(titles(&body["completed"]), ["B", "C"]);
+
+    let req = test::TestRequest::get().uri("/api/todos/board?completed=true").to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn dry_run_lists_without_changing_anything() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let milk = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let eggs = TodoFixture::new("Buy eggs").completed().insert(&ctx.pool).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch-delete?dry_run=true")
+        .set_json(json!({ "ids": [milk, eggs, Uuid::new_v4()] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["requested"], 3);
+    assert_eq!(body["deleted"], 2);
+    assert_eq!(body["todos"].as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos/complete-all?dry_run=true")
+        .to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["updated"], 1);
+    assert_eq!(body["todos"][0]["id"], milk.to_string());
+    assert_eq!(body["todos"][0]["completed"], false);
+
+    let req = test::TestRequest::get().uri("/api/todos?completed=false").to_request();
+    let (_, pending) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(pending.as_array().unwrap().len(), 1);
+    assert_eq!(pending[0]["id"], milk.to_string());
+}
+
+/// A parent with a pending subtask, which itself has one, rows listed root first
+async fn seed_subtree(ctx: &TestContext) -> [Uuid; 3] {
+    let party = TodoFixture::new("Plan party").insert(&ctx.pool).await;
+    let cake = TodoFixture::new("Order cake").insert(&ctx.pool).await;
+    let candles = TodoFixture::new("Buy candles").insert(&ctx.pool).await;
+    for (parent, child) in [(party, cake), (cake, candles)] {
+        sqlx::query("UPDATE todos SET parent_id = $1 WHERE id = $2")
+            .bind(parent)
+            .bind(child)
+            .execute(&ctx.pool)
+            .await
+            .unwrap();
+    }
+    [party, cake, candles]
+}
+
+#[actix_web::test]
+async fn batch_delete_refuses_to_orphan_subtasks() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let [party, cake, candles] = seed_subtree(&ctx).await;
+    let milk = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    for query in ["", "?hard=true", "?dry_run=true"] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/todos/batch-delete{}", query))
+            .set_json(json!({ "ids": [milk, party, cake] }))
+            .to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{}", query);
+        assert_eq!(
+            body["message"],
+            format!(
+                "Todo with id {} has subtasks that are not being deleted; \
+                 pass cascade=true to delete them too",
+                cake
+            )
+        );
+    }
+    let live: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(live, 4);
+
+    // With every subtask in the batch nothing is left behind
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch-delete")
+        .set_json(json!({ "ids": [party, cake, candles] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted"], 3);
+
+    // A soft-deleted subtask still goes with its parent on a hard delete, so it blocks it too
+    sqlx::query("UPDATE todos SET deleted_at = NULL WHERE id = $1")
+        .bind(party)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch-delete?hard=true")
+        .set_json(json!({ "ids": [party] }))
+        .to_request();
+    let (status, _) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn batch_delete_cascades_to_subtasks() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let [party, cake, candles] = seed_subtree(&ctx).await;
+    let milk = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let mut events = ctx.bus.subscribe(CurrentUser(None));
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch-delete?cascade=true&dry_run=true")
+        .set_json(json!({ "ids": [party] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["requested"], 1);
+    assert_eq!(body["deleted"], 3);
+
+    let req = test::TestRequest::post()
+        .uri("/api/todos/batch-delete?cascade=true&hard=true")
+        .set_json(json!({ "ids": [party] }))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted"], 3);
+
+    let mut deleted = Vec::new();
+    for _ in 0..3 {
+        match events.recv().await {
+            Some(TodoEvent::Deleted { id }) => deleted.push(id),
+            other => panic!("Expected a deleted event, got {:?}", other),
+        }
+    }
+    deleted.sort();
+    let mut expected = vec![party, cake, candles];
+    expected.sort();
+    assert_eq!(deleted, expected);
+
+    let remaining: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM todos")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, [milk]);
+}
+
+#[actix_web::test]
+async fn purge_completed_leaves_pending_todos_alone() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let milk = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    TodoFixture::new("Buy eggs").completed().insert(&ctx.pool).await;
+    TodoFixture::new("Buy bread").completed().insert(&ctx.pool).await;
+    // Deleting this one would take its pending subtask with it
+    let party = TodoFixture::new("Plan party").completed().insert(&ctx.pool).await;
+    let cake = TodoFixture::new("Order cake").insert(&ctx.pool).await;
+    sqlx::query("UPDATE todos SET parent_id = $1 WHERE id = $2")
+        .bind(party)
+        .bind(cake)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::delete()
+        .uri("/api/todos/completed?dry_run=true")
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted"], 2);
+    assert_eq!(body["todos"].as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::delete().uri("/api/todos/completed").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "deleted": 2 }));
+
+    let req = test::TestRequest::get().uri("/api/todos?sort_by=title&order=asc").to_request();
+    let (_, listed) = read_json(test::call_service(&app, req).await).await;
+    let ids: Vec<_> = listed.as_array().unwrap().iter().map(|t| t["id"].clone()).collect();
+    assert_eq!(ids, [json!(milk), json!(cake), json!(party)]);
+
+    // A hard purge also removes the ones already soft-deleted
+    let req = test::TestRequest::delete().uri("/api/todos/completed?hard=true").to_request();
+    let (_, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(body["deleted"], 2);
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(rows, 3);
+}
+
+#[actix_web::test]
+async fn fields_limits_the_returned_keys() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    // Every key of a full todo can be selected
+    let req = test::TestRequest::get().uri(&format!("/api/todos/{}", id)).to_request();
+    let (_, full) = read_json(test::call_service(&app, req).await).await;
+    let mut keys: Vec<_> = full.as_object().unwrap().keys().map(String::as_str).collect();
+    let mut known = TODO_FIELDS.to_vec();
+    keys.sort_unstable();
+    known.sort_unstable();
+    assert_eq!(keys, known);
+
+    let req = test::TestRequest::get().uri("/api/todos?fields=id,title").to_request();
+    let (status, list) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list, json!([{ "id": id, "title": "Buy milk" }]));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos/{}?fields=title,colour", id))
+        .to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "BAD_REQUEST");
+}
+
+#[actix_web::test]
+async fn created_range_filters_the_list() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    for (title, day) in [("Old", 1), ("This week", 15), ("Today", 20)] {
+        TodoFixture::new(title)
+            .created_at(Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap())
+            .insert(&ctx.pool)
+            .await;
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos?created_after=2024-01-15T00:00:00Z&created_before=2024-01-20T00:00:00Z")
+        .to_request();
+    let (status, list) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["title"], "This week");
+
+    for query in [
+        "created_after=last-week",
+        "created_after=2024-02-01T00:00:00Z&created_before=2024-01-01T00:00:00Z",
+    ] {
+        let req = test::TestRequest::get().uri(&format!("/api/todos?{}", query)).to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        assert_eq!(body["error"], "BAD_REQUEST");
+    }
+}
+
+#[actix_web::test]
+async fn tz_converts_created_and_updated_at() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk")
+        .created_at(Utc.with_ymd_and_hms(2024, 7, 1, 9, 30, 0).unwrap())
+        .insert(&ctx.pool)
+        .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/todos/{}?tz=Europe/Bucharest", id))
+        .to_request();
+    let (status, todo) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["created_at"], "2024-07-01T12:30:00+03:00");
+    assert_eq!(todo["updated_at"], "2024-07-01T12:30:00+03:00");
+
+    let req = test::TestRequest::get()
+        .uri("/api/todos?tz=America/New_York&fields=id,created_at")
+        .to_request();
+    let (_, list) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(list, json!([{ "id": id, "created_at": "2024-07-01T05:30:00-04:00" }]));
+
+    let req = test::TestRequest::get().uri("/api/todos?tz=Mars/Olympus").to_request();
+    let (status, body) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "BAD_REQUEST");
+}
+
+#[actix_web::test]
+async fn titles_are_trimmed_and_collapsed() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+
+    for (title, stored) in [
+        ("  Buy milk  ", "Buy milk"),
+        ("Buy\n\tmilk \r\n now", "Buy milk now"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let (status, created) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["title"], stored);
+    }
+
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/todos/{}", id))
+        .set_json(json!({ "title": " Buy  bread " }))
+        .to_request();
+    let (_, patched) = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(patched["title"], "Buy bread");
+}
+
+#[actix_web::test]
+async fn blank_and_control_character_titles_are_rejected() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = test::init_service(ctx.app()).await;
+    let id = TodoFixture::new("Buy milk").insert(&ctx.pool).await;
+
+    for (title, message) in [
+        ("\t\t", "Title cannot be empty"),
+        ("\n \r\n", "Title cannot be empty"),
+        ("Buy\u{7} milk", "Title cannot contain control characters"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/todos")
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let (status, body) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", title);
+        assert_eq!(body["fields"][0]["message"], message);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/todos/{}", id))
+            .set_json(json!({ "title": title, "completed": false }))
+            .to_request();
+        let (status, _) = read_json(test::call_service(&app, req).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", title);
+    }
+}
@@ -5,6 +5,13 @@
 
 // API Configuration
 const API_BASE_URL = 'http://localhost:8080/api';
+// Must match the server's API_KEY; leave empty when the server runs without one
+const API_KEY = '';
+
+// Headers sent with every API request
+function apiHeaders(extra = {}) {
+    return API_KEY ? { ...extra, 'X-API-Key': API_KEY } : extra;
+}
 
 // State Management
 let allTodos = [];
@@ -35,7 +42,9 @@ async function fetchTodos() {
         emptyMessage.style.display = 'none';
         todosList.innerHTML = '';
 
-        const response = await fetch(`${API_BASE_URL}/todos`);
+        const response = await fetch(`${API_BASE_URL}/todos?limit=100`, {
+            headers: apiHeaders(),
+        });
 
         if (!response.ok) {
             throw new Error(`Failed to fetch todos: ${response.statusText}`);
@@ -56,9 +65,9 @@ async function createTodo(title, description) {
     try {
         const response = await fetch(`${API_BASE_URL}/todos`, {
             method: 'POST',
-            headers: {
+            headers: apiHeaders({
                 'Content-Type': 'application/json',
-            },
+            }),
             body: JSON.stringify({
                 title: title.trim(),
                 description: description.trim() || null,
@@ -88,10 +97,10 @@ async function createTodo(title, description) {
 async function updateTodo(id, updates) {
     try {
         const response = await fetch(`${API_BASE_URL}/todos/${id}`, {
-            method: 'PUT',
-            headers: {
+            method: 'PATCH',
+            headers: apiHeaders({
                 'Content-Type': 'application/json',
-            },
+            }),
             body: JSON.stringify(updates),
         });
 
@@ -118,6 +127,7 @@ async function deleteTodo(id) {
     try {
         const response = await fetch(`${API_BASE_URL}/todos/${id}`, {
             method: 'DELETE',
+            headers: apiHeaders(),
         });
 
         if (!response.ok) {